@@ -0,0 +1,2 @@
+-- id of the accessory from assets/accessories.ron the user wears on The Jouch in this guild
+ALTER TABLE users ADD COLUMN IF NOT EXISTS accessory TEXT;
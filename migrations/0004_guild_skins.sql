@@ -0,0 +1,7 @@
+CREATE TABLE IF NOT EXISTS guild_skins (
+    guild_id BIGINT PRIMARY KEY,
+    image BYTEA NOT NULL,
+    seats JSON NOT NULL,
+    avatar_size INT NOT NULL DEFAULT 128,
+    updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
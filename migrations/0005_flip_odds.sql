@@ -0,0 +1,6 @@
+ALTER TYPE jouch_orientation ADD VALUE IF NOT EXISTS 'Tilted';
+ALTER TYPE jouch_orientation ADD VALUE IF NOT EXISTS 'Levitating';
+ALTER TYPE jouch_orientation ADD VALUE IF NOT EXISTS 'Orbit';
+
+-- weights for where /flip lands The Jouch in this guild, NULL for the defaults (see src/flip_table.rs)
+ALTER TABLE guilds ADD COLUMN IF NOT EXISTS flip_table JSON;
@@ -0,0 +1,11 @@
+-- every /flip & /rectify, so there's a history of who moved The Jouch and when
+CREATE TABLE IF NOT EXISTS flip_events (
+    id BIGINT PRIMARY KEY GENERATED ALWAYS AS IDENTITY,
+    guild_id BIGINT NOT NULL,
+    user_id BIGINT NOT NULL,
+    old_orientation jouch_orientation NOT NULL,
+    new_orientation jouch_orientation NOT NULL,
+    flipped_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS flip_events_guild_time ON flip_events (guild_id, flipped_at);
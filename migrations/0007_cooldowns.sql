@@ -0,0 +1,3 @@
+-- seconds users have to wait between uses of each command in this guild, where it differs from the defaults
+-- (see src/commands/cooldown.rs)
+ALTER TABLE guilds ADD COLUMN IF NOT EXISTS cooldowns JSON;
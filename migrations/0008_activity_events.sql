@@ -0,0 +1,14 @@
+CREATE TYPE activity_kind AS ENUM ('Sit','Flip');
+
+-- every sit & flip, alongside the lifetime counts in users, so rankings can cover just a recent period.
+-- anything from before this table existed is only in the lifetime counts.
+CREATE TABLE IF NOT EXISTS activity_events (
+    id BIGINT PRIMARY KEY GENERATED ALWAYS AS IDENTITY,
+    guild_id BIGINT NOT NULL,
+    user_id BIGINT NOT NULL,
+    kind activity_kind NOT NULL,
+    happened_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS activity_events_guild_time ON activity_events (guild_id, happened_at);
+CREATE INDEX IF NOT EXISTS activity_events_user_time ON activity_events (user_id, happened_at);
@@ -0,0 +1,8 @@
+-- daily sit streaks, counted in the guild's time zone; sit_streak is as of last_sit_day,
+-- so it's only still going if that was today or yesterday.
+ALTER TABLE users ADD COLUMN IF NOT EXISTS sit_streak INT NOT NULL DEFAULT 0;
+ALTER TABLE users ADD COLUMN IF NOT EXISTS best_sit_streak INT NOT NULL DEFAULT 0;
+ALTER TABLE users ADD COLUMN IF NOT EXISTS last_sit_day DATE;
+
+-- IANA name of the guild's time zone (e.g. America/Chicago), NULL for UTC
+ALTER TABLE guilds ADD COLUMN IF NOT EXISTS time_zone TEXT;
@@ -0,0 +1,17 @@
+-- achievements each user has unlocked in each guild; ids come from assets/achievements.ron
+CREATE TABLE IF NOT EXISTS achievements (
+    guild_id BIGINT NOT NULL,
+    user_id BIGINT NOT NULL,
+    achievement TEXT NOT NULL,
+    unlocked_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (guild_id, user_id, achievement)
+);
+
+-- everyone each user has sat on The Jouch with, in both directions
+CREATE TABLE IF NOT EXISTS sit_companions (
+    guild_id BIGINT NOT NULL,
+    user_id BIGINT NOT NULL,
+    friend_id BIGINT NOT NULL,
+    first_sat_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (guild_id, user_id, friend_id)
+);
@@ -0,0 +1,9 @@
+-- whether a guild shows up on (and counts towards) the global leaderboard; off unless an admin turns it on
+ALTER TABLE guilds ADD COLUMN IF NOT EXISTS global_rankings BOOLEAN NOT NULL DEFAULT FALSE;
+
+-- settings that belong to a user everywhere, rather than to a user in one guild
+CREATE TABLE IF NOT EXISTS user_settings (
+    user_id BIGINT PRIMARY KEY,
+    -- keep the user off the global leaderboard, even in guilds that are on it
+    hide_from_global BOOLEAN NOT NULL DEFAULT FALSE
+);
@@ -0,0 +1,3 @@
+-- whether friends get asked before being sat on The Jouch with someone, or always/never get sat with
+CREATE TYPE sit_consent AS ENUM ('Ask','Always','Never');
+ALTER TABLE user_settings ADD COLUMN IF NOT EXISTS sit_consent sit_consent NOT NULL DEFAULT 'Ask';
@@ -0,0 +1,2 @@
+-- games of musical chairs on The Jouch won by the user
+ALTER TABLE users ADD COLUMN IF NOT EXISTS games_won INT NOT NULL DEFAULT 0;
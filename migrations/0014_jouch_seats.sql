@@ -0,0 +1,10 @@
+-- who's on The Jouch in each guild right now; they get up again on their own after a while
+CREATE TABLE IF NOT EXISTS jouch_seats (
+    guild_id BIGINT NOT NULL,
+    user_id BIGINT NOT NULL,
+    sat_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (guild_id, user_id)
+);
+
+-- total time the user's spent on The Jouch, counted when they get up
+ALTER TABLE users ADD COLUMN IF NOT EXISTS seconds_seated BIGINT NOT NULL DEFAULT 0;
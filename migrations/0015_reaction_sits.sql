@@ -0,0 +1,4 @@
+-- sitting on The Jouch with someone by reacting to their message; off unless an admin turns it on
+ALTER TABLE guilds ADD COLUMN IF NOT EXISTS reaction_sits BOOLEAN NOT NULL DEFAULT FALSE;
+-- the emoji to react with, as text for a unicode emoji or <:name:id> for a custom one; NULL for the default
+ALTER TABLE guilds ADD COLUMN IF NOT EXISTS reaction_emoji TEXT;
@@ -0,0 +1,108 @@
+// Cosmetic accessories users can wear while sitting on The Jouch, described by a catalog in the assets directory.
+use std::collections::HashSet;
+use std::path::Path;
+
+use anyhow::bail;
+use serde::Deserialize;
+
+pub const CATALOG_FILE: &str = "accessories.ron";
+// anchors are given for an avatar this size, and get scaled along with the avatar.
+pub const REFERENCE_AVATAR_SIZE: u32 = 128;
+
+// Where an accessory goes; birthday hats take the place of anything worn on the head.
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub enum Slot {
+    Head,
+    Face,
+}
+
+// What it takes to be able to wear an accessory, counted per server.
+#[derive(Debug, Deserialize, Clone, Copy)]
+pub enum Unlock {
+    Sits(i32),
+    Flips(i32),
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Accessory {
+    // what gets stored in the database & typed into /accessory equip
+    pub id: String,
+    pub name: String,
+    pub image: String,
+    pub slot: Slot,
+    // where the top left corner of the image goes, relative to the top left corner of the avatar
+    pub anchor: (i32, i32),
+    // accessories without one are available to everyone
+    #[serde(default)]
+    pub unlock: Option<Unlock>,
+}
+
+impl Unlock {
+    // (progress, goal) for a user with the given counts.
+    pub fn progress(&self, sit_count: i32, flip_count: i32) -> (i32, i32) {
+        match self {
+            Unlock::Sits(goal) => (sit_count, *goal),
+            Unlock::Flips(goal) => (flip_count, *goal),
+        }
+    }
+
+    pub fn describe(&self) -> String {
+        match self {
+            Unlock::Sits(goal) => format!("sit on The Jouch {goal} times"),
+            Unlock::Flips(goal) => format!("flip The Jouch {goal} times"),
+        }
+    }
+}
+
+impl Accessory {
+    pub fn is_unlocked(&self, sit_count: i32, flip_count: i32) -> bool {
+        self.unlock.is_none_or(|unlock| {
+            let (progress, goal) = unlock.progress(sit_count, flip_count);
+            progress >= goal
+        })
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(transparent)]
+pub struct AccessoryCatalog {
+    accessories: Vec<Accessory>,
+}
+
+impl AccessoryCatalog {
+    pub fn load(dir: &Path) -> anyhow::Result<Self> {
+        let catalog: Self = ron::de::from_bytes(&std::fs::read(dir.join(CATALOG_FILE))?)?;
+
+        let mut ids = HashSet::new();
+        for accessory in &catalog.accessories {
+            if !ids.insert(&accessory.id) {
+                bail!("Accessory id {} is used more than once", accessory.id);
+            }
+            // ids end up in render cache keys, which use these as separators.
+            if accessory.id.contains(['+', ',', '/']) {
+                bail!(
+                    "Accessory id {} can't contain '+', ',' or '/'",
+                    accessory.id
+                );
+            }
+        }
+
+        Ok(catalog)
+    }
+
+    pub fn get(&self, id: &str) -> Option<&Accessory> {
+        self.accessories
+            .iter()
+            .find(|accessory| accessory.id.eq_ignore_ascii_case(id))
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Accessory> {
+        self.accessories.iter()
+    }
+
+    pub fn images(&self) -> impl Iterator<Item = &str> {
+        self.accessories
+            .iter()
+            .map(|accessory| accessory.image.as_str())
+    }
+}
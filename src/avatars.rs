@@ -0,0 +1,252 @@
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use anyhow::anyhow;
+use image::{Rgba, RgbaImage};
+use serenity::all::UserId;
+use serenity::prelude::TypeMapKey;
+use tracing::{debug, warn};
+
+use crate::cache::ByteCache;
+use the_jouch::compositing::{self, Mask, Placement};
+
+const FETCH_TIMEOUT: Duration = Duration::from_secs(5);
+const FETCH_ATTEMPTS: u32 = 3;
+// how long to wait before the first retry, doubled for each retry after that.
+const RETRY_DELAY: Duration = Duration::from_millis(250);
+
+// Limits on how much avatar data to hang on to, in memory & on disk.
+const MAX_MEMORY_BYTES: usize = 64 * 1024 * 1024;
+const MAX_DISK_BYTES: u64 = 256 * 1024 * 1024;
+
+// Background colors Discord uses for default avatars.
+const DEFAULT_AVATAR_COLORS: [Rgba<u8>; 6] = [
+    Rgba([88, 101, 242, 255]),
+    Rgba([117, 126, 138, 255]),
+    Rgba([59, 165, 93, 255]),
+    Rgba([250, 166, 26, 255]),
+    Rgba([237, 66, 69, 255]),
+    Rgba([235, 69, 158, 255]),
+];
+
+// Cache of raw avatar images keyed by avatar hash, so repeated sits don't hit Discord's CDN every time.
+// Avatars with a given hash never change, so entries never need to be invalidated; just evicted when space runs low.
+pub struct AvatarCache {
+    client: reqwest::Client,
+    entries: Mutex<ByteCache>,
+    disk_dir: Option<PathBuf>,
+    // roughly how much is in disk_dir, so it can be pruned once it goes over MAX_DISK_BYTES.
+    disk_bytes: AtomicU64,
+    // keeps temporary file names unique when the same avatar is written twice at once.
+    writes: AtomicU64,
+}
+
+// keys end up as file names, so make sure they can't go anywhere they shouldn't.
+fn file_key(key: &str) -> String {
+    key.chars()
+        .filter(|c| c.is_ascii_alphanumeric() || *c == '_')
+        .collect()
+}
+
+impl AvatarCache {
+    pub fn new(disk_dir: Option<PathBuf>) -> anyhow::Result<Self> {
+        let mut disk_bytes = 0;
+        if let Some(dir) = &disk_dir {
+            std::fs::create_dir_all(dir)?;
+            match prune_disk(dir) {
+                Ok(kept) => disk_bytes = kept,
+                Err(err) => warn!("Unable to prune avatar cache in {:?}: {:?}", dir, err),
+            }
+        }
+
+        Ok(Self {
+            client: reqwest::Client::builder().timeout(FETCH_TIMEOUT).build()?,
+            entries: Mutex::new(ByteCache::new(MAX_MEMORY_BYTES)),
+            disk_dir,
+            disk_bytes: AtomicU64::new(disk_bytes),
+            writes: AtomicU64::new(0),
+        })
+    }
+
+    // Get the avatar with the given key, from memory, then disk, then finally downloading it from url.
+    pub async fn get(&self, key: &str, url: &str) -> anyhow::Result<Arc<[u8]>> {
+        let key = file_key(key);
+
+        if let Some(data) = self.entries.lock().unwrap().get(&key) {
+            return Ok(data);
+        }
+
+        let disk_path = self.disk_dir.as_ref().map(|dir| dir.join(&key));
+
+        let on_disk = match &disk_path {
+            Some(path) => tokio::fs::read(path).await.ok(),
+            None => None,
+        };
+
+        let data: Arc<[u8]> = match on_disk {
+            Some(data) => data.into(),
+            None => {
+                let data: Arc<[u8]> = self.fetch(url).await?.into();
+                if let Some(path) = &disk_path {
+                    // Not critical; we can always download it again.
+                    if let Err(err) = self.write_to_disk(path, &data).await {
+                        warn!("Unable to write avatar to {:?}: {:?}", path, err);
+                    }
+                }
+                data
+            }
+        };
+
+        self.entries.lock().unwrap().insert(key, data.clone());
+
+        Ok(data)
+    }
+
+    // Written somewhere else first & moved into place, so nobody ever reads a half written avatar.
+    async fn write_to_disk(&self, path: &Path, data: &[u8]) -> anyhow::Result<()> {
+        let write = self.writes.fetch_add(1, Ordering::Relaxed);
+        let temp_path = path.with_extension(format!("{write}.tmp"));
+        tokio::fs::write(&temp_path, data).await?;
+        if let Err(err) = tokio::fs::rename(&temp_path, path).await {
+            let _ = tokio::fs::remove_file(&temp_path).await;
+            return Err(err.into());
+        }
+
+        let total = self
+            .disk_bytes
+            .fetch_add(data.len() as u64, Ordering::Relaxed)
+            + data.len() as u64;
+        if total > MAX_DISK_BYTES {
+            if let Some(dir) = self.disk_dir.clone() {
+                let kept = tokio::task::spawn_blocking(move || prune_disk(&dir)).await??;
+                self.disk_bytes.store(kept, Ordering::Relaxed);
+            }
+        }
+
+        Ok(())
+    }
+
+    // Drop an avatar that turned out to be broken, so it gets downloaded again next time rather than reused.
+    pub async fn forget(&self, key: &str) {
+        let key = file_key(key);
+        self.entries.lock().unwrap().remove(&key);
+        if let Some(dir) = &self.disk_dir {
+            let path = dir.join(&key);
+            if let Err(err) = tokio::fs::remove_file(&path).await {
+                if err.kind() != std::io::ErrorKind::NotFound {
+                    warn!("Unable to delete avatar {:?}: {:?}", path, err);
+                }
+            }
+        }
+    }
+
+    async fn fetch(&self, url: &str) -> anyhow::Result<Vec<u8>> {
+        let mut delay = RETRY_DELAY;
+        let mut last_error = anyhow!("No attempts made to fetch {url}");
+
+        for attempt in 1..=FETCH_ATTEMPTS {
+            let result = async {
+                anyhow::Ok(
+                    self.client
+                        .get(url)
+                        .send()
+                        .await?
+                        .error_for_status()?
+                        .bytes()
+                        .await?
+                        .to_vec(),
+                )
+            }
+            .await;
+
+            match result {
+                Ok(data) => return Ok(data),
+                Err(err) => {
+                    debug!("Attempt {attempt} to fetch avatar {url} failed: {err}");
+                    last_error = err;
+                }
+            }
+
+            if attempt < FETCH_ATTEMPTS {
+                tokio::time::sleep(delay).await;
+                delay *= 2;
+            }
+        }
+
+        Err(last_error)
+    }
+}
+
+impl TypeMapKey for AvatarCache {
+    type Value = Arc<AvatarCache>;
+}
+
+// Delete the oldest files in the avatar cache directory until it fits in MAX_DISK_BYTES, giving how much is left.
+fn prune_disk(dir: &Path) -> anyhow::Result<u64> {
+    let mut files = Vec::new();
+    for entry in std::fs::read_dir(dir)? {
+        let entry = entry?;
+        let metadata = entry.metadata()?;
+        if metadata.is_file() {
+            files.push((metadata.modified()?, metadata.len(), entry.path()));
+        }
+    }
+
+    // newest first, so everything after the budget runs out gets deleted.
+    files.sort_by_key(|(modified, _, _)| std::cmp::Reverse(*modified));
+
+    let mut total = 0;
+    let mut kept = 0;
+    for (_, len, path) in files {
+        total += len;
+        if total > MAX_DISK_BYTES {
+            std::fs::remove_file(path)?;
+        } else {
+            kept += len;
+        }
+    }
+
+    Ok(kept)
+}
+
+// Stand-in avatar for when the real one can't be fetched: a simple face on one of Discord's default avatar colors.
+pub fn default_avatar(user: UserId, size: u32) -> RgbaImage {
+    // same formula Discord uses to pick a default avatar
+    let background = DEFAULT_AVATAR_COLORS[((user.get() >> 22) % 6) as usize];
+    let mut avatar = RgbaImage::new(size, size);
+    compositing::draw(
+        &mut avatar,
+        &RgbaImage::from_pixel(size, size, background),
+        &Placement::at(0.0, 0.0).mask(Mask::Circle),
+    );
+
+    let white = Rgba([255, 255, 255, 255]);
+    let unit = size as f32 / 16.0;
+    // eyes
+    for x in [5.5, 10.5] {
+        compositing::draw_line(
+            &mut avatar,
+            (x * unit, 5.5 * unit),
+            (x * unit, 7.0 * unit),
+            unit,
+            white,
+        );
+    }
+    // smile, as a handful of short segments around the bottom of a circle
+    let points: Vec<(f32, f32)> = (0..=8)
+        .map(|i| {
+            let angle = (30.0 + 15.0 * i as f32).to_radians();
+            (
+                (8.0 + 4.0 * angle.cos()) * unit,
+                (8.0 + 4.0 * angle.sin()) * unit,
+            )
+        })
+        .collect();
+    for segment in points.windows(2) {
+        compositing::draw_line(&mut avatar, segment[0], segment[1], unit * 0.75, white);
+    }
+
+    avatar
+}
@@ -0,0 +1,159 @@
+// Render a sit image from local files, without Discord or a database.
+// Handy for trying out new scenes & accessories, and for golden image tests of the compositor;
+// given the same arguments (including --date), the output is always the same.
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use anyhow::{anyhow, bail};
+use chrono::{Local, NaiveDate};
+use rand::{rngs::StdRng, SeedableRng};
+use the_jouch::render::{
+    load_animation, render_scene, sit_layers, JouchAssets, JouchOrientation, Seat,
+};
+use the_jouch::scenes::avatar_sizes;
+
+const USAGE: &str = "usage: jouch-render [options] --avatar PATH [--hat] [--accessory ID] [--avatar PATH ...]
+
+  --avatar PATH        image (PNG, GIF, WebP...) of someone to sit on The Jouch; repeat for friends
+  --hat                give the avatar before it a birthday hat
+  --accessory ID       have the avatar before it wear an accessory from accessories.ron
+  --orientation NAME   Normal (default), UpsideDown, RotatedLeft, RotatedRight, Tilted, Levitating or Orbit
+  --scene NAME         use this scene from scenes.ron for every panel, rather than picking them
+  --date YYYY-MM-DD    date to pick seasonal scenes for (defaults to today)
+  --seed N             seed for choosing between scenes with the same number of seats (defaults to 0)
+  --assets DIR         where to find scenes.ron, accessories.ron & the images (defaults to assets)
+  --out PATH           where to write the image (defaults to jouch.png, or jouch.gif if animated)";
+
+struct AvatarArg {
+    path: PathBuf,
+    hat: bool,
+    accessory: Option<String>,
+}
+
+struct Args {
+    avatars: Vec<AvatarArg>,
+    orientation: JouchOrientation,
+    scene: Option<String>,
+    date: NaiveDate,
+    seed: u64,
+    assets: PathBuf,
+    out: Option<PathBuf>,
+}
+
+fn parse_args() -> anyhow::Result<Args> {
+    let mut args = Args {
+        avatars: Vec::new(),
+        orientation: JouchOrientation::Normal,
+        scene: None,
+        date: Local::now().date_naive(),
+        seed: 0,
+        assets: PathBuf::from("assets"),
+        out: None,
+    };
+
+    let mut argv = std::env::args().skip(1);
+    while let Some(arg) = argv.next() {
+        let mut value = || argv.next().ok_or(anyhow!("{arg} needs a value"));
+        match arg.as_str() {
+            "--avatar" => args.avatars.push(AvatarArg {
+                path: value()?.into(),
+                hat: false,
+                accessory: None,
+            }),
+            "--hat" => {
+                args.avatars
+                    .last_mut()
+                    .ok_or(anyhow!("--hat has to come after an --avatar"))?
+                    .hat = true
+            }
+            "--accessory" => {
+                let accessory = value()?;
+                args.avatars
+                    .last_mut()
+                    .ok_or(anyhow!("--accessory has to come after an --avatar"))?
+                    .accessory = Some(accessory)
+            }
+            "--orientation" => {
+                let orientation = value()?;
+                args.orientation = JouchOrientation::from_str(&orientation)
+                    .map_err(|_| anyhow!("Unknown orientation {orientation}"))?
+            }
+            "--scene" => args.scene = Some(value()?),
+            "--date" => args.date = NaiveDate::parse_from_str(&value()?, "%Y-%m-%d")?,
+            "--seed" => args.seed = value()?.parse()?,
+            "--assets" => args.assets = value()?.into(),
+            "--out" => args.out = Some(value()?.into()),
+            "--help" | "-h" => {
+                println!("{USAGE}");
+                std::process::exit(0);
+            }
+            _ => bail!("Unknown argument {arg}"),
+        }
+    }
+
+    if args.avatars.is_empty() {
+        bail!("Nobody to sit on The Jouch; pass at least one --avatar");
+    }
+
+    Ok(args)
+}
+
+fn render(args: Args) -> anyhow::Result<PathBuf> {
+    let assets = JouchAssets::load(&args.assets)?;
+
+    let panels = if let Some(name) = &args.scene {
+        let scene = assets
+            .scenes
+            .scenes
+            .iter()
+            .find(|scene| &scene.name == name)
+            .ok_or(anyhow!("No scene named {name}"))?;
+        // as many copies as it takes to seat everyone.
+        vec![scene; args.avatars.len().div_ceil(scene.seats.len())]
+    } else {
+        assets.scenes.panels(
+            args.avatars.len(),
+            args.date,
+            None,
+            &mut StdRng::seed_from_u64(args.seed),
+        )
+    };
+
+    let mut seats = Vec::new();
+    for (avatar, size) in args.avatars.into_iter().zip(avatar_sizes(&panels)) {
+        let face = load_animation(&avatar.path)
+            .map_err(|err| anyhow!("Unable to load {:?}: {}", avatar.path, err))?;
+        seats.push(Seat {
+            face: face.resize(size),
+            hat: avatar.hat,
+            accessory: avatar.accessory,
+        });
+    }
+
+    let mut panel_images = Vec::new();
+    for scene in panels {
+        panel_images.push((scene, assets.get(&scene.base_image)?.clone()));
+    }
+
+    let (layers, width, height) = sit_layers(&assets, panel_images, seats)?;
+    let (image_bytes, format) = render_scene(&layers, width, height, args.orientation)?;
+
+    let out = args
+        .out
+        .unwrap_or_else(|| PathBuf::from(format!("jouch.{}", format.extensions_str()[0])));
+    std::fs::write(&out, image_bytes)?;
+
+    Ok(out)
+}
+
+fn main() {
+    let result = parse_args().and_then(render);
+
+    match result {
+        Ok(out) => println!("{}", out.display()),
+        Err(err) => {
+            eprintln!("{err}\n\n{USAGE}");
+            std::process::exit(1);
+        }
+    }
+}
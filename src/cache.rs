@@ -0,0 +1,60 @@
+use std::collections::{HashMap, VecDeque};
+use std::sync::Arc;
+
+// Least recently used cache of raw bytes, bounded by the total size of everything in it.
+pub struct ByteCache {
+    data: HashMap<String, Arc<[u8]>>,
+    // least recently used first
+    order: VecDeque<String>,
+    size: usize,
+    max_size: usize,
+}
+
+impl ByteCache {
+    pub fn new(max_size: usize) -> Self {
+        Self {
+            data: HashMap::new(),
+            order: VecDeque::new(),
+            size: 0,
+            max_size,
+        }
+    }
+
+    pub fn get(&mut self, key: &str) -> Option<Arc<[u8]>> {
+        let data = self.data.get(key)?.clone();
+        self.touch(key);
+        Some(data)
+    }
+
+    fn touch(&mut self, key: &str) {
+        if let Some(index) = self.order.iter().position(|k| k == key) {
+            if let Some(key) = self.order.remove(index) {
+                self.order.push_back(key);
+            }
+        }
+    }
+
+    pub fn insert(&mut self, key: String, data: Arc<[u8]>) {
+        if let Some(old) = self.data.insert(key.clone(), data.clone()) {
+            self.size -= old.len();
+            self.touch(&key);
+        } else {
+            self.order.push_back(key);
+        }
+        self.size += data.len();
+
+        // always keep the newest entry, even if it's too big on its own.
+        while self.size > self.max_size && self.order.len() > 1 {
+            if let Some(evicted) = self.order.pop_front().and_then(|k| self.data.remove(&k)) {
+                self.size -= evicted.len();
+            }
+        }
+    }
+
+    pub fn remove(&mut self, key: &str) {
+        if let Some(old) = self.data.remove(key) {
+            self.size -= old.len();
+            self.order.retain(|k| k != key);
+        }
+    }
+}
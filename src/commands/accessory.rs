@@ -0,0 +1,140 @@
+use anyhow::anyhow;
+use serenity::all::{
+    CommandDataOptionValue, CommandInteraction, Context, CreateEmbed, EditInteractionResponse,
+    GuildId, MessageBuilder, UserId,
+};
+
+use super::sit::jouch_assets;
+use crate::db::{Db, UserKey};
+use crate::CommandResult;
+
+async fn equip(
+    ctx: &Context,
+    guild: GuildId,
+    user: UserId,
+    id: Option<&str>,
+) -> CommandResult<String> {
+    let assets = jouch_assets(ctx).await?;
+    let data = ctx.data.read().await;
+    let db = data.get::<Db>().ok_or(anyhow!("Unable to get database"))?;
+
+    let key = UserKey {
+        user: user.into(),
+        guild: guild.into(),
+    };
+
+    let Some(id) = id else {
+        db.update(&key, "accessory", None::<String>).await?;
+        return Ok("Took off your accessory.".to_owned());
+    };
+
+    let accessory = assets.accessories.get(id).ok_or(anyhow!(
+        "Unknown accessory {id}; use /accessory list to see what there is."
+    ))?;
+
+    // users without any data yet haven't done anything to unlock with.
+    let user_data = db.read(&key).await?.unwrap_or_default();
+    if !accessory.is_unlocked(user_data.sit_count, user_data.flip_count) {
+        if let Some(unlock) = accessory.unlock {
+            let (progress, goal) = unlock.progress(user_data.sit_count, user_data.flip_count);
+            return Err(anyhow!(
+                "You haven't unlocked the {} yet! To unlock it, {} ({progress}/{goal}).",
+                accessory.name,
+                unlock.describe()
+            ));
+        }
+    }
+
+    db.update(&key, "accessory", &accessory.id).await?;
+
+    let mut builder = MessageBuilder::new();
+    builder
+        .push("You're now wearing the ")
+        .push_bold_safe(&accessory.name)
+        .push(" on The Jouch.");
+    Ok(builder.build())
+}
+
+async fn list(ctx: &Context, guild: GuildId, user: UserId) -> CommandResult<CreateEmbed> {
+    let assets = jouch_assets(ctx).await?;
+    let data = ctx.data.read().await;
+    let db = data.get::<Db>().ok_or(anyhow!("Unable to get database"))?;
+
+    let user_data = db
+        .read(&UserKey {
+            user: user.into(),
+            guild: guild.into(),
+        })
+        .await?
+        .unwrap_or_default();
+    let equipped = user_data.accessory.as_deref();
+
+    let mut embed = CreateEmbed::default().title("Accessories");
+
+    for accessory in assets.accessories.iter() {
+        let status = if equipped == Some(accessory.id.as_str()) {
+            "Wearing".to_owned()
+        } else if accessory.is_unlocked(user_data.sit_count, user_data.flip_count) {
+            "Unlocked".to_owned()
+        } else if let Some(unlock) = accessory.unlock {
+            let (progress, goal) = unlock.progress(user_data.sit_count, user_data.flip_count);
+            format!("Locked: {} ({progress}/{goal})", unlock.describe())
+        } else {
+            "Locked".to_owned()
+        };
+
+        embed = embed.field(
+            format!("{} (`{}`)", accessory.name, accessory.id),
+            status,
+            false,
+        );
+    }
+
+    Ok(embed)
+}
+
+pub async fn accessory(ctx: &Context, command: &CommandInteraction) -> CommandResult {
+    let subcommand = command
+        .data
+        .options
+        .first()
+        .ok_or(anyhow!("Please provide a valid subcommand"))?;
+    let guild = command
+        .guild_id
+        .ok_or(anyhow!("Unable to get guild where command was sent"))?;
+
+    match subcommand.name.as_str() {
+        "equip" => {
+            let id = if let CommandDataOptionValue::SubCommand(subcommand_args) = &subcommand.value
+            {
+                subcommand_args.iter().find_map(|arg| {
+                    if let CommandDataOptionValue::String(id) = &arg.value {
+                        Some(id.as_str())
+                    } else {
+                        None
+                    }
+                })
+            } else {
+                None
+            };
+
+            let response = equip(ctx, guild, command.user.id, id).await?;
+
+            command
+                .edit_response(&ctx, EditInteractionResponse::new().content(response))
+                .await?;
+
+            Ok(())
+        }
+        "list" => {
+            let embed = list(ctx, guild, command.user.id).await?;
+
+            command
+                .edit_response(&ctx, EditInteractionResponse::new().add_embed(embed))
+                .await?;
+
+            Ok(())
+        }
+        _ => Err(anyhow!("Unknown option {}", subcommand.name)),
+    }
+}
@@ -0,0 +1,295 @@
+use anyhow::{anyhow, bail};
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use serenity::all::{
+    CommandInteraction, Context, CreateEmbed, EditInteractionResponse, GuildId, MessageBuilder,
+    ResolvedValue, User,
+};
+use serenity::prelude::TypeMapKey;
+use std::collections::HashSet;
+use std::path::Path;
+use std::sync::Arc;
+
+use super::sit::ActivityKind;
+use super::timezone::guild_day_start;
+use crate::db::{Db, UserData, UserKey};
+use crate::CommandResult;
+
+// Achievements users can unlock on The Jouch, described by a catalog in the assets directory.
+pub const ACHIEVEMENTS_FILE: &str = "achievements.ron";
+
+// What it takes to unlock an achievement, counted per server.
+#[derive(Debug, Deserialize, Clone, Copy)]
+pub enum Rule {
+    Sits(i32),
+    Flips(i32),
+    // flips within a single day, in the server's time zone
+    FlipsInDay(i64),
+    // different people sat with, counting both whoever brought you along & whoever you brought
+    Friends(i64),
+    // best daily sit streak
+    Streak(i32),
+    // sitting on The Jouch on your birthday
+    BirthdaySit,
+}
+
+// Everything rules get checked against, for one user in one server.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Stats {
+    pub sit_count: i32,
+    pub flip_count: i32,
+    pub best_sit_streak: i32,
+    pub flips_today: i64,
+    pub friends: i64,
+    // whether the sit being checked happened on the user's birthday
+    pub birthday_sit: bool,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Achievement {
+    // what gets stored in the database
+    pub id: String,
+    pub name: String,
+    pub rule: Rule,
+}
+
+impl Rule {
+    // (progress, goal) for a user with the given stats.
+    pub fn progress(&self, stats: &Stats) -> (i64, i64) {
+        match self {
+            Rule::Sits(goal) => (stats.sit_count.into(), (*goal).into()),
+            Rule::Flips(goal) => (stats.flip_count.into(), (*goal).into()),
+            Rule::FlipsInDay(goal) => (stats.flips_today, *goal),
+            Rule::Friends(goal) => (stats.friends, *goal),
+            Rule::Streak(goal) => (stats.best_sit_streak.into(), (*goal).into()),
+            Rule::BirthdaySit => (stats.birthday_sit.into(), 1),
+        }
+    }
+
+    pub fn is_met(&self, stats: &Stats) -> bool {
+        let (progress, goal) = self.progress(stats);
+        progress >= goal
+    }
+
+    pub fn describe(&self) -> String {
+        match self {
+            Rule::Sits(1) => "sit on The Jouch".to_owned(),
+            Rule::Sits(goal) => format!("sit on The Jouch {goal} times"),
+            Rule::Flips(1) => "flip The Jouch".to_owned(),
+            Rule::Flips(goal) => format!("flip The Jouch {goal} times"),
+            Rule::FlipsInDay(goal) => format!("flip The Jouch {goal} times in one day"),
+            Rule::Friends(goal) => format!("sit with {goal} different friends"),
+            Rule::Streak(goal) => format!("sit on The Jouch {goal} days in a row"),
+            Rule::BirthdaySit => "sit on The Jouch on your birthday".to_owned(),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(transparent)]
+pub struct AchievementCatalog {
+    achievements: Vec<Achievement>,
+}
+
+impl AchievementCatalog {
+    pub fn load(dir: &Path) -> anyhow::Result<Self> {
+        let catalog: Self = ron::de::from_bytes(&std::fs::read(dir.join(ACHIEVEMENTS_FILE))?)?;
+
+        let mut ids = HashSet::new();
+        for achievement in &catalog.achievements {
+            if !ids.insert(&achievement.id) {
+                bail!("Achievement id {} is used more than once", achievement.id);
+            }
+        }
+
+        Ok(catalog)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Achievement> {
+        self.achievements.iter()
+    }
+}
+
+pub struct AchievementsKey;
+
+impl TypeMapKey for AchievementsKey {
+    type Value = Arc<AchievementCatalog>;
+}
+
+pub async fn achievement_catalog(ctx: &Context) -> CommandResult<Arc<AchievementCatalog>> {
+    ctx.data
+        .read()
+        .await
+        .get::<AchievementsKey>()
+        .cloned()
+        .ok_or(anyhow!("Unable to get achievements"))
+}
+
+// Remember who sat on The Jouch together, in both directions.
+pub async fn record_companions(db: &Db, guild: GuildId, seated: &[&User]) -> CommandResult {
+    for user in seated {
+        for friend in seated {
+            if user.id == friend.id {
+                continue;
+            }
+            sqlx::query(
+                "INSERT INTO sit_companions (guild_id, user_id, friend_id) VALUES ($1, $2, $3)
+                    ON CONFLICT DO NOTHING",
+            )
+            .bind(guild.get() as i64)
+            .bind(user.id.get() as i64)
+            .bind(friend.id.get() as i64)
+            .execute(db.pool())
+            .await?;
+        }
+    }
+    Ok(())
+}
+
+async fn read_stats(
+    db: &Db,
+    key: &UserKey,
+    user_data: &UserData,
+    birthday_sit: bool,
+) -> CommandResult<Stats> {
+    let (flips_today,): (i64,) = sqlx::query_as(
+        "SELECT COUNT(*) FROM activity_events
+            WHERE guild_id = $1 AND user_id = $2 AND kind = $3 AND happened_at >= $4",
+    )
+    .bind(key.guild)
+    .bind(key.user)
+    .bind(ActivityKind::Flip)
+    .bind(guild_day_start(db, GuildId::new(key.guild as u64)).await?)
+    .fetch_one(db.pool())
+    .await?;
+
+    let (friends,): (i64,) =
+        sqlx::query_as("SELECT COUNT(*) FROM sit_companions WHERE guild_id = $1 AND user_id = $2")
+            .bind(key.guild)
+            .bind(key.user)
+            .fetch_one(db.pool())
+            .await?;
+
+    Ok(Stats {
+        sit_count: user_data.sit_count,
+        flip_count: user_data.flip_count,
+        best_sit_streak: user_data.best_sit_streak,
+        flips_today,
+        friends,
+        birthday_sit,
+    })
+}
+
+// Unlock anything the user has earned since last time, returning a message announcing each one.
+pub async fn check_achievements(
+    db: &Db,
+    catalog: &AchievementCatalog,
+    user: &User,
+    key: &UserKey,
+    user_data: &UserData,
+    birthday_sit: bool,
+) -> CommandResult<Vec<String>> {
+    let stats = read_stats(db, key, user_data, birthday_sit).await?;
+
+    let mut announcements = Vec::new();
+    for achievement in catalog
+        .iter()
+        .filter(|achievement| achievement.rule.is_met(&stats))
+    {
+        let result = sqlx::query(
+            "INSERT INTO achievements (guild_id, user_id, achievement) VALUES ($1, $2, $3)
+                ON CONFLICT DO NOTHING",
+        )
+        .bind(key.guild)
+        .bind(key.user)
+        .bind(&achievement.id)
+        .execute(db.pool())
+        .await?;
+
+        // already unlocked before now
+        if result.rows_affected() == 0 {
+            continue;
+        }
+
+        announcements.push(
+            MessageBuilder::new()
+                .push("\u{1F3C6} ")
+                .mention(user)
+                .push(" unlocked ")
+                .push_bold_safe(&achievement.name)
+                .push(format!(": {}!", achievement.rule.describe()))
+                .build(),
+        );
+    }
+
+    Ok(announcements)
+}
+
+pub async fn achievements(ctx: &Context, command: &CommandInteraction) -> CommandResult {
+    let guild = command
+        .guild_id
+        .ok_or(anyhow!("Unable to get guild where command was sent"))?;
+    let mut user = &command.user;
+    for arg in command.data.options() {
+        if let ("user", ResolvedValue::User(arg_user, _)) = (arg.name, arg.value) {
+            user = arg_user;
+        }
+    }
+
+    let catalog = achievement_catalog(ctx).await?;
+    let data = ctx.data.read().await;
+    let db = data.get::<Db>().ok_or(anyhow!("Unable to get database"))?;
+
+    let key = UserKey {
+        user: user.id.into(),
+        guild: guild.into(),
+    };
+    let user_data = db.read(&key).await?.unwrap_or_default();
+    let stats = read_stats(db, &key, &user_data, false).await?;
+
+    let unlocked: Vec<(String, DateTime<Utc>)> = sqlx::query_as(
+        "SELECT achievement, unlocked_at FROM achievements WHERE guild_id = $1 AND user_id = $2",
+    )
+    .bind(key.guild)
+    .bind(key.user)
+    .fetch_all(db.pool())
+    .await?;
+
+    let unlocked_count = catalog
+        .iter()
+        .filter(|achievement| unlocked.iter().any(|(id, _)| *id == achievement.id))
+        .count();
+
+    let mut embed = CreateEmbed::default()
+        .title(format!("{}'s achievements", user.display_name()))
+        .description(format!(
+            "{unlocked_count} of {} unlocked",
+            catalog.iter().count()
+        ));
+
+    for achievement in catalog.iter() {
+        let status =
+            if let Some((_, unlocked_at)) = unlocked.iter().find(|(id, _)| *id == achievement.id) {
+                format!("Unlocked <t:{}:D>", unlocked_at.timestamp())
+            } else {
+                let (progress, goal) = achievement.rule.progress(&stats);
+                if goal > 1 {
+                    format!(
+                        "Locked: {} ({}/{goal})",
+                        achievement.rule.describe(),
+                        progress.min(goal)
+                    )
+                } else {
+                    format!("Locked: {}", achievement.rule.describe())
+                }
+            };
+
+        embed = embed.field(&achievement.name, status, false);
+    }
+
+    command
+        .edit_response(&ctx, EditInteractionResponse::new().add_embed(embed))
+        .await?;
+
+    Ok(())
+}
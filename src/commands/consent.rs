@@ -0,0 +1,267 @@
+use anyhow::anyhow;
+use chrono::Utc;
+use enum_utils::FromStr;
+use serde::{Deserialize, Serialize};
+use serenity::all::{
+    ButtonStyle, CommandInteraction, Context, CreateActionRow, CreateAllowedMentions, CreateButton,
+    CreateInteractionResponse, CreateInteractionResponseMessage, CreateMessage,
+    EditInteractionResponse, EditMessage, Message, MessageBuilder, User,
+};
+use serenity::futures::StreamExt;
+use std::time::Duration;
+
+use super::sit::SitRefused;
+use crate::db::Db;
+use crate::CommandResult;
+
+// How long friends have to answer before the sit goes ahead without them.
+const CONSENT_TIMEOUT: Duration = Duration::from_secs(120);
+
+// Whether a user wants to be asked before someone sits on The Jouch with them.
+#[derive(
+    Eq, PartialEq, Debug, Serialize, Deserialize, Clone, Copy, Default, FromStr, sqlx::Type,
+)]
+#[sqlx(type_name = "sit_consent")]
+#[enumeration(case_insensitive)]
+pub enum SitConsent {
+    #[default]
+    Ask,
+    #[enumeration(alias = "AlwaysAllow")]
+    Always,
+    #[enumeration(alias = "NeverAllow")]
+    Never,
+}
+
+impl SitConsent {
+    pub fn describe(&self) -> &str {
+        match self {
+            SitConsent::Ask => "You get asked before anyone sits on The Jouch with you.",
+            SitConsent::Always => "Anyone can sit on The Jouch with you without asking.",
+            SitConsent::Never => "Nobody can sit on The Jouch with you.",
+        }
+    }
+}
+
+#[derive(Eq, PartialEq, Clone, Copy)]
+enum Answer {
+    Waiting,
+    Accepted,
+    Declined,
+}
+
+fn invitation(user: &User, asked: &[(&User, Answer)], deadline: i64, done: bool) -> String {
+    let mut builder = MessageBuilder::new();
+    builder
+        .mention(user)
+        .push(" wants to sit on The Jouch with you!");
+    if !done {
+        builder.push(format!(" Answer <t:{deadline}:R>."));
+    }
+    for (friend, answer) in asked {
+        builder.push("\n").push(match answer {
+            Answer::Waiting if done => "\u{231B} ",
+            Answer::Waiting => "\u{2754} ",
+            Answer::Accepted => "\u{2705} ",
+            Answer::Declined => "\u{274C} ",
+        });
+        builder.mention(*friend);
+    }
+    builder.build()
+}
+
+fn consent_buttons() -> Vec<CreateActionRow> {
+    vec![CreateActionRow::Buttons(vec![
+        CreateButton::new("sit_accept")
+            .style(ButtonStyle::Success)
+            .label("Sit"),
+        CreateButton::new("sit_decline")
+            .style(ButtonStyle::Secondary)
+            .label("No thanks"),
+    ])]
+}
+
+// Where to ask: in the response to a command, or in a reply to a message.
+pub enum AskIn<'a> {
+    Command(&'a CommandInteraction),
+    Reply(&'a Message),
+}
+
+impl AskIn<'_> {
+    async fn post(
+        &self,
+        ctx: &Context,
+        content: String,
+        mentions: CreateAllowedMentions,
+    ) -> CommandResult<Message> {
+        Ok(match self {
+            AskIn::Command(command) => {
+                command
+                    .edit_response(
+                        &ctx,
+                        EditInteractionResponse::new()
+                            .content(content)
+                            .allowed_mentions(mentions)
+                            .components(consent_buttons()),
+                    )
+                    .await?
+            }
+            AskIn::Reply(msg) => {
+                msg.channel_id
+                    .send_message(
+                        &ctx,
+                        CreateMessage::new()
+                            .content(content)
+                            .reference_message(*msg)
+                            .allowed_mentions(mentions)
+                            .components(consent_buttons()),
+                    )
+                    .await?
+            }
+        })
+    }
+
+    async fn close(&self, ctx: &Context, invite: &mut Message, content: String) -> CommandResult {
+        match self {
+            AskIn::Command(command) => {
+                command
+                    .edit_response(
+                        &ctx,
+                        EditInteractionResponse::new()
+                            .content(content)
+                            .components(vec![]),
+                    )
+                    .await?;
+            }
+            AskIn::Reply(_) => {
+                invite
+                    .edit(&ctx, EditMessage::new().content(content).components(vec![]))
+                    .await?;
+            }
+        }
+        Ok(())
+    }
+}
+
+// Check with each friend before the user sits with them, going by their standing preference if they have one.
+// Returns whoever agreed, in the order they were given, and a note about each friend who didn't;
+// if none of them agreed, the sit is refused instead.
+pub async fn ask_friends<'a>(
+    ctx: &Context,
+    ask_in: AskIn<'_>,
+    user: &User,
+    friends: &[&'a User],
+) -> CommandResult<(Vec<&'a User>, Vec<String>)> {
+    let mut asked = Vec::new();
+    let mut refused = Vec::new();
+    {
+        let data = ctx.data.read().await;
+        let db = data.get::<Db>().ok_or(anyhow!("Unable to get database"))?;
+        for friend in friends {
+            // bots can't press buttons, and nobody needs to ask themselves.
+            if friend.bot || friend.id == user.id {
+                continue;
+            }
+            let settings = db.read_user_settings(friend.id).await?.unwrap_or_default();
+            match settings.sit_consent {
+                SitConsent::Ask => asked.push((*friend, Answer::Waiting)),
+                SitConsent::Always => {}
+                SitConsent::Never => refused.push(friend.id),
+            }
+        }
+    }
+
+    if !asked.is_empty() {
+        let deadline = (Utc::now() + CONSENT_TIMEOUT).timestamp();
+        let mut invite = ask_in
+            .post(
+                ctx,
+                invitation(user, &asked, deadline, false),
+                CreateAllowedMentions::new().users(asked.iter().map(|(friend, _)| friend.id)),
+            )
+            .await?;
+        let mut interactions = invite
+            .await_component_interaction(ctx)
+            .timeout(CONSENT_TIMEOUT)
+            .stream();
+
+        while let Some(interaction) = interactions.next().await {
+            let Some((_, answer)) = asked.iter_mut().find(|(friend, answer)| {
+                friend.id == interaction.user.id && *answer == Answer::Waiting
+            }) else {
+                interaction
+                    .create_response(
+                        &ctx,
+                        CreateInteractionResponse::Message(
+                            CreateInteractionResponseMessage::new()
+                                .content("This invitation isn't waiting on you.")
+                                .ephemeral(true),
+                        ),
+                    )
+                    .await?;
+                continue;
+            };
+            *answer = if interaction.data.custom_id == "sit_accept" {
+                Answer::Accepted
+            } else {
+                Answer::Declined
+            };
+
+            let done = asked.iter().all(|(_, answer)| *answer != Answer::Waiting);
+            interaction
+                .create_response(
+                    &ctx,
+                    CreateInteractionResponse::UpdateMessage(
+                        CreateInteractionResponseMessage::new()
+                            .content(invitation(user, &asked, deadline, done))
+                            .components(if done { vec![] } else { consent_buttons() }),
+                    ),
+                )
+                .await?;
+            if done {
+                break;
+            }
+        }
+
+        // anyone still waiting ran out of time, and the buttons shouldn't work any more.
+        if asked.iter().any(|(_, answer)| *answer == Answer::Waiting) {
+            ask_in
+                .close(ctx, &mut invite, invitation(user, &asked, deadline, true))
+                .await?;
+        }
+    }
+
+    let mut agreed = Vec::new();
+    let mut notes = Vec::new();
+    for friend in friends {
+        let answer = asked
+            .iter()
+            .find(|(asked_friend, _)| asked_friend.id == friend.id)
+            .map(|(_, answer)| *answer);
+        let note = match answer {
+            Some(Answer::Accepted) => None,
+            Some(Answer::Declined) => Some(" would rather not sit on The Jouch right now."),
+            Some(Answer::Waiting) => Some(" didn't answer in time."),
+            None if refused.contains(&friend.id) => {
+                Some(" doesn't let anyone sit on The Jouch with them.")
+            }
+            None => None,
+        };
+        if let Some(note) = note {
+            notes.push(
+                MessageBuilder::new()
+                    .push_bold_safe(friend.display_name())
+                    .push(note)
+                    .build(),
+            );
+        } else {
+            agreed.push(*friend);
+        }
+    }
+
+    // the sit was meant to be with someone, so it's off if nobody wants to.
+    if !friends.is_empty() && agreed.is_empty() {
+        return Err(SitRefused(notes.join("\n")).into());
+    }
+
+    Ok((agreed, notes))
+}
@@ -0,0 +1,236 @@
+use anyhow::{anyhow, bail};
+use chrono::Duration;
+use serenity::all::{
+    CommandInteraction, Context, CreateEmbed, EditInteractionResponse, GuildId, ResolvedValue,
+    UserId,
+};
+use serenity::prelude::TypeMapKey;
+use std::collections::{BTreeMap, HashMap};
+use std::convert::TryFrom;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+use super::jouch::describe_duration;
+use super::sit::SIT_WITH_COMMAND;
+use crate::db::Db;
+use crate::CommandResult;
+
+// Seconds each user has to wait between uses of a command, for any commands a guild has changed.
+pub type CooldownTable = BTreeMap<String, u32>;
+
+// Commands that can be given a cooldown with /cooldown set, and the cooldown they have by default.
+pub const COOLDOWN_COMMANDS: [(&str, u32); 6] = [
+    ("sit", 30),
+    ("flip", 60),
+    ("rectify", 60),
+    ("rankings", 0),
+    ("jouch", 0),
+    ("achievements", 0),
+];
+
+pub const MAX_COOLDOWN_SECONDS: u32 = 24 * 60 * 60;
+
+fn cooldown_seconds(table: Option<&CooldownTable>, command: &str) -> u32 {
+    table
+        .and_then(|table| table.get(command))
+        .or_else(|| {
+            COOLDOWN_COMMANDS
+                .iter()
+                .find(|(name, _)| *name == command)
+                .map(|(_, seconds)| seconds)
+        })
+        .copied()
+        .unwrap_or_default()
+}
+
+// When each user can next use each command in each guild.
+// Only kept in memory; a restart letting everyone off early isn't a big deal.
+#[derive(Default)]
+pub struct Cooldowns(Mutex<HashMap<(String, GuildId, UserId), Instant>>);
+
+impl Cooldowns {
+    // How long the user has left to wait if they're still cooling down, otherwise starts a new cooldown.
+    fn check(
+        &self,
+        command: &str,
+        guild: GuildId,
+        user: UserId,
+        cooldown: std::time::Duration,
+    ) -> Option<std::time::Duration> {
+        let now = Instant::now();
+        let mut ready_at = self.0.lock().unwrap();
+        // forget anyone who's done cooling down, so this doesn't grow forever.
+        ready_at.retain(|_, ready_at| *ready_at > now);
+
+        let key = (command.to_owned(), guild, user);
+        if let Some(ready_at) = ready_at.get(&key) {
+            return Some(*ready_at - now);
+        }
+        if !cooldown.is_zero() {
+            ready_at.insert(key, now + cooldown);
+        }
+        None
+    }
+
+    // Let the user off a cooldown they started, e.g. because the command didn't work.
+    fn refund(&self, command: &str, guild: GuildId, user: UserId) {
+        self.0
+            .lock()
+            .unwrap()
+            .remove(&(command.to_owned(), guild, user));
+    }
+}
+
+impl TypeMapKey for Cooldowns {
+    type Value = Arc<Cooldowns>;
+}
+
+// Right-click menu entries share the cooldown of the command they stand in for.
+fn cooldown_name(command: &CommandInteraction) -> &str {
+    match command.data.name.as_str() {
+        SIT_WITH_COMMAND => "sit",
+        name => name,
+    }
+}
+
+// How long until the user can use the command again, if they're on cooldown for it.
+// Using a command starts its cooldown, so this should only be called once the command is definitely going to run;
+// if it fails after all, refund_cooldown hands the cooldown back.
+pub async fn check_user_cooldown(
+    ctx: &Context,
+    command: &str,
+    guild: GuildId,
+    user: UserId,
+) -> CommandResult<Option<Duration>> {
+    let data = ctx.data.read().await;
+    let db = data.get::<Db>().ok_or(anyhow!("Unable to get database"))?;
+    let cooldowns = data
+        .get::<Cooldowns>()
+        .ok_or(anyhow!("Unable to get cooldowns"))?;
+
+    let table = db.read_guild(guild).await?.and_then(|data| data.cooldowns);
+    let seconds = cooldown_seconds(table.as_ref(), command);
+
+    Ok(cooldowns
+        .check(
+            command,
+            guild,
+            user,
+            std::time::Duration::from_secs(seconds as u64),
+        )
+        // round up, so it never says to wait 0 seconds
+        .map(|remaining| Duration::seconds(remaining.as_secs() as i64 + 1)))
+}
+
+pub async fn check_cooldown(
+    ctx: &Context,
+    command: &CommandInteraction,
+) -> CommandResult<Option<Duration>> {
+    let Some(guild) = command.guild_id else {
+        return Ok(None);
+    };
+    check_user_cooldown(ctx, cooldown_name(command), guild, command.user.id).await
+}
+
+pub async fn refund_user_cooldown(ctx: &Context, command: &str, guild: GuildId, user: UserId) {
+    if let Some(cooldowns) = ctx.data.read().await.get::<Cooldowns>() {
+        cooldowns.refund(command, guild, user);
+    }
+}
+
+pub async fn refund_cooldown(ctx: &Context, command: &CommandInteraction) {
+    if let Some(guild) = command.guild_id {
+        refund_user_cooldown(ctx, cooldown_name(command), guild, command.user.id).await;
+    }
+}
+
+pub fn cooldown_message(command: &CommandInteraction, remaining: Duration) -> String {
+    format!(
+        "Slow down! You can use {} again in {}.",
+        command.data.name,
+        describe_duration(remaining)
+    )
+}
+
+fn cooldowns_embed(table: Option<&CooldownTable>) -> CreateEmbed {
+    let mut embed = CreateEmbed::default()
+        .title("Cooldowns")
+        .description("How long everyone has to wait between uses of each command.");
+
+    for (command, _) in COOLDOWN_COMMANDS {
+        let seconds = cooldown_seconds(table, command);
+        embed = embed.field(
+            format!("/{command}"),
+            if seconds == 0 {
+                "None".to_owned()
+            } else {
+                describe_duration(Duration::seconds(seconds as i64))
+            },
+            true,
+        );
+    }
+
+    embed
+}
+
+pub async fn cooldown(ctx: &Context, command: &CommandInteraction) -> CommandResult {
+    let guild = command
+        .guild_id
+        .ok_or(anyhow!("Unable to get guild where command was sent"))?;
+    let options = command.data.options();
+    let subcommand = options
+        .first()
+        .ok_or(anyhow!("Please provide a valid subcommand"))?;
+
+    let data = ctx.data.read().await;
+    let db = data.get::<Db>().ok_or(anyhow!("Unable to get database"))?;
+
+    let mut table = db.read_guild(guild).await?.and_then(|data| data.cooldowns);
+
+    match (subcommand.name, &subcommand.value) {
+        ("show", _) => {}
+        ("set", ResolvedValue::SubCommand(args)) => {
+            let mut name = None;
+            let mut seconds = None;
+            for arg in args {
+                match (arg.name, &arg.value) {
+                    ("command", ResolvedValue::String(value)) => {
+                        name = COOLDOWN_COMMANDS
+                            .iter()
+                            .find(|(command, _)| command == value)
+                            .map(|(command, _)| *command)
+                    }
+                    ("seconds", ResolvedValue::Integer(value)) => {
+                        seconds = u32::try_from(*value)
+                            .ok()
+                            .filter(|seconds| *seconds <= MAX_COOLDOWN_SECONDS)
+                    }
+                    _ => bail!("Unknown/unimplemented option {}", arg.name),
+                }
+            }
+
+            table.get_or_insert_with(Default::default).insert(
+                name.ok_or(anyhow!("Invalid command value passed!"))?
+                    .to_owned(),
+                seconds.ok_or(anyhow!("Invalid seconds value passed!"))?,
+            );
+            db.update_guild(guild, "cooldowns", sqlx::types::Json(&table))
+                .await?;
+        }
+        ("reset", _) => {
+            table = None;
+            db.update_guild(guild, "cooldowns", None::<sqlx::types::Json<CooldownTable>>)
+                .await?;
+        }
+        _ => bail!("Unknown option {}", subcommand.name),
+    }
+
+    command
+        .edit_response(
+            &ctx,
+            EditInteractionResponse::new().add_embed(cooldowns_embed(table.as_ref())),
+        )
+        .await?;
+
+    Ok(())
+}
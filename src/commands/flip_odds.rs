@@ -0,0 +1,138 @@
+use anyhow::{anyhow, bail};
+use serenity::all::{
+    CommandInteraction, Context, CreateEmbed, EditInteractionResponse, GuildId, ResolvedValue,
+};
+use std::convert::TryFrom;
+use std::str::FromStr;
+use the_jouch::flip_table::FlipTable;
+
+use super::sit::JouchOrientation;
+use crate::db::Db;
+use crate::CommandResult;
+
+// Upper limit on a single weight, so percentages of them can't overflow.
+pub const MAX_FLIP_WEIGHT: i64 = 10_000;
+
+pub async fn read_flip_table(db: &Db, guild: GuildId) -> CommandResult<FlipTable> {
+    Ok(db
+        .read_guild(guild)
+        .await?
+        .and_then(|data| data.flip_table)
+        .unwrap_or_default())
+}
+
+async fn write_flip_table(db: &Db, guild: GuildId, table: &FlipTable) -> CommandResult {
+    if table.weights.values().all(|weight| *weight == 0) {
+        bail!("At least one orientation needs a chance of coming up!");
+    }
+    db.update_guild(guild, "flip_table", sqlx::types::Json(table))
+        .await?;
+    Ok(())
+}
+
+fn odds_embed(table: &FlipTable, current: JouchOrientation) -> CreateEmbed {
+    let odds = table.odds(current);
+    let total: u32 = odds.iter().map(|(_, weight)| weight).sum();
+
+    let mut embed = CreateEmbed::default()
+        .title("Flip odds")
+        .description(format!(
+            "The Jouch is {} right now. Landing the same way again keeps {}% of the usual weight.",
+            current.name(),
+            table.repeat_percent
+        ));
+
+    for orientation in JouchOrientation::ALL {
+        let chance = odds
+            .iter()
+            .find(|(o, _)| *o == orientation)
+            .map(|(_, weight)| *weight as f64 * 100.0 / total as f64)
+            .unwrap_or_default();
+        embed = embed.field(
+            format!("{} (`{orientation:?}`)", orientation.name()),
+            format!(
+                "weight {}, {chance:.1}% on the next flip",
+                table.weight(orientation)
+            ),
+            true,
+        );
+    }
+
+    embed
+}
+
+pub async fn flip_odds(ctx: &Context, command: &CommandInteraction) -> CommandResult {
+    let guild = command
+        .guild_id
+        .ok_or(anyhow!("Unable to get guild where command was sent"))?;
+    let options = command.data.options();
+    let subcommand = options
+        .first()
+        .ok_or(anyhow!("Please provide a valid subcommand"))?;
+
+    let data = ctx.data.read().await;
+    let db = data.get::<Db>().ok_or(anyhow!("Unable to get database"))?;
+
+    let guild_data = db.read_guild(guild).await?.unwrap_or_default();
+    let mut table = guild_data.flip_table.unwrap_or_default();
+
+    match (subcommand.name, &subcommand.value) {
+        ("show", _) => {}
+        ("set", ResolvedValue::SubCommand(args)) => {
+            let mut orientation = None;
+            let mut weight = None;
+            for arg in args {
+                match (arg.name, &arg.value) {
+                    ("orientation", ResolvedValue::String(value)) => {
+                        orientation = Some(
+                            JouchOrientation::from_str(value)
+                                .map_err(|_| anyhow!("Invalid orientation value passed!"))?,
+                        )
+                    }
+                    ("weight", ResolvedValue::Integer(value)) => {
+                        weight = Some(
+                            u32::try_from(*value)
+                                .ok()
+                                .filter(|weight| *weight as i64 <= MAX_FLIP_WEIGHT)
+                                .ok_or(anyhow!("Invalid weight value passed!"))?,
+                        )
+                    }
+                    _ => bail!("Unknown/unimplemented option {}", arg.name),
+                }
+            }
+
+            table.weights.insert(
+                orientation.ok_or(anyhow!("No orientation passed"))?,
+                weight.ok_or(anyhow!("No weight passed"))?,
+            );
+            write_flip_table(db, guild, &table).await?;
+        }
+        ("repeat", ResolvedValue::SubCommand(args)) => {
+            for arg in args {
+                if let ("percent", ResolvedValue::Integer(value)) = (arg.name, &arg.value) {
+                    table.repeat_percent = u32::try_from(*value)
+                        .ok()
+                        .filter(|percent| *percent <= 100)
+                        .ok_or(anyhow!("Invalid percent value passed!"))?;
+                }
+            }
+            write_flip_table(db, guild, &table).await?;
+        }
+        ("reset", _) => {
+            table = FlipTable::default();
+            db.update_guild(guild, "flip_table", None::<sqlx::types::Json<FlipTable>>)
+                .await?;
+        }
+        _ => bail!("Unknown option {}", subcommand.name),
+    }
+
+    command
+        .edit_response(
+            &ctx,
+            EditInteractionResponse::new()
+                .add_embed(odds_embed(&table, guild_data.jouch_orientation)),
+        )
+        .await?;
+
+    Ok(())
+}
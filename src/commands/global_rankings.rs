@@ -0,0 +1,91 @@
+use anyhow::{anyhow, bail};
+use serenity::all::{CommandInteraction, Context, EditInteractionResponse, ResolvedValue};
+use std::str::FromStr;
+
+use super::consent::SitConsent;
+use crate::db::Db;
+use crate::CommandResult;
+
+// Put this server on the global leaderboard, or take it off again.
+pub async fn global_rankings(ctx: &Context, command: &CommandInteraction) -> CommandResult {
+    let guild = command
+        .guild_id
+        .ok_or(anyhow!("Unable to get guild where command was sent"))?;
+    let options = command.data.options();
+    let subcommand = options
+        .first()
+        .ok_or(anyhow!("Please provide a valid subcommand"))?;
+
+    let data = ctx.data.read().await;
+    let db = data.get::<Db>().ok_or(anyhow!("Unable to get database"))?;
+
+    let message = match subcommand.name {
+        "join" => {
+            db.update_guild(guild, "global_rankings", true).await?;
+            "This server now counts towards the global leaderboard (`/rankings scope: Global`). \
+                Members can keep themselves off it with `/privacy`."
+        }
+        "leave" => {
+            db.update_guild(guild, "global_rankings", false).await?;
+            "This server no longer counts towards the global leaderboard."
+        }
+        _ => bail!("Unknown option {}", subcommand.name),
+    };
+
+    command
+        .edit_response(&ctx, EditInteractionResponse::new().content(message))
+        .await?;
+
+    Ok(())
+}
+
+// Check or change whether the user shows up on the global leaderboard, and who can sit on The Jouch with them.
+pub async fn privacy(ctx: &Context, command: &CommandInteraction) -> CommandResult {
+    let mut show = None;
+    let mut sit_consent = None;
+    for arg in command.data.options() {
+        match (arg.name, arg.value) {
+            ("global_rankings", ResolvedValue::Boolean(value)) => show = Some(value),
+            ("sit_with", ResolvedValue::String(value)) => {
+                sit_consent = Some(
+                    SitConsent::from_str(value)
+                        .map_err(|_| anyhow!("Unknown sit preference {value}"))?,
+                )
+            }
+            _ => bail!("Unknown/unimplemented option {}", arg.name),
+        }
+    }
+
+    let data = ctx.data.read().await;
+    let db = data.get::<Db>().ok_or(anyhow!("Unable to get database"))?;
+
+    if let Some(show) = show {
+        db.update_user_settings(command.user.id, "hide_from_global", !show)
+            .await?;
+    }
+    if let Some(sit_consent) = sit_consent {
+        db.update_user_settings(command.user.id, "sit_consent", sit_consent)
+            .await?;
+    }
+    let settings = db
+        .read_user_settings(command.user.id)
+        .await?
+        .unwrap_or_default();
+
+    command
+        .edit_response(
+            &ctx,
+            EditInteractionResponse::new().content(format!(
+                "{}\n{}",
+                if settings.hide_from_global {
+                    "You're kept off the global leaderboard, and your sits & flips don't count towards any server's global total."
+                } else {
+                    "You show up on the global leaderboard for any server that's joined it."
+                },
+                settings.sit_consent.describe()
+            )),
+        )
+        .await?;
+
+    Ok(())
+}
@@ -0,0 +1,194 @@
+use anyhow::anyhow;
+use chrono::{DateTime, Duration, Utc};
+use serenity::all::{
+    CommandInteraction, Context, CreateEmbed, EditInteractionResponse, GuildId, ResolvedValue,
+    UserId,
+};
+use sqlx::FromRow;
+
+use super::musical_chairs::musical_chairs;
+use super::seats::read_occupants;
+use super::sit::JouchOrientation;
+use crate::db::Db;
+use crate::CommandResult;
+
+#[derive(Debug, Clone, FromRow)]
+pub struct FlipEvent {
+    pub user_id: i64,
+    pub old_orientation: JouchOrientation,
+    pub new_orientation: JouchOrientation,
+    pub flipped_at: DateTime<Utc>,
+}
+
+pub async fn record_flip(
+    db: &Db,
+    guild: GuildId,
+    user: UserId,
+    old: JouchOrientation,
+    new: JouchOrientation,
+) -> CommandResult {
+    sqlx::query(
+        "INSERT INTO flip_events (guild_id, user_id, old_orientation, new_orientation)
+            VALUES ($1, $2, $3, $4)",
+    )
+    .bind(guild.get() as i64)
+    .bind(user.get() as i64)
+    .bind(old)
+    .bind(new)
+    .execute(db.pool())
+    .await?;
+    Ok(())
+}
+
+// e.g. "3 days, 4 hours"; just the biggest two units, nobody needs the seconds on a week long streak.
+pub fn describe_duration(duration: Duration) -> String {
+    const UNITS: [(&str, i64); 4] = [
+        ("day", 86400),
+        ("hour", 3600),
+        ("minute", 60),
+        ("second", 1),
+    ];
+
+    let seconds = duration.num_seconds().max(0);
+    let Some(biggest) = UNITS.iter().position(|(_, size)| seconds >= *size) else {
+        return "0 seconds".to_owned();
+    };
+
+    UNITS[biggest..]
+        .iter()
+        .take(2)
+        .scan(seconds, |remaining, (name, size)| {
+            let count = *remaining / size;
+            *remaining %= size;
+            Some((count, name))
+        })
+        .filter(|(count, _)| *count > 0)
+        .map(|(count, name)| format!("{count} {name}{}", if count == 1 { "" } else { "s" }))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+async fn status(db: &Db, guild: GuildId) -> CommandResult<CreateEmbed> {
+    let orientation = db
+        .read_guild(guild)
+        .await?
+        .map(|data| data.jouch_orientation)
+        .unwrap_or_default();
+
+    let last_flip: Option<FlipEvent> = sqlx::query_as(
+        "SELECT user_id, old_orientation, new_orientation, flipped_at FROM flip_events
+            WHERE guild_id = $1 ORDER BY flipped_at DESC LIMIT 1",
+    )
+    .bind(guild.get() as i64)
+    .fetch_optional(db.pool())
+    .await?;
+
+    // flips that land the same way up don't count as a change
+    let last_change: Option<(DateTime<Utc>,)> = sqlx::query_as(
+        "SELECT flipped_at FROM flip_events
+            WHERE guild_id = $1 AND old_orientation <> new_orientation
+            ORDER BY flipped_at DESC LIMIT 1",
+    )
+    .bind(guild.get() as i64)
+    .fetch_optional(db.pool())
+    .await?;
+
+    // each change to upright lasts until the next change, or until now if it's still upright.
+    let longest_upright: Option<(i64, bool)> = sqlx::query_as(
+        "SELECT EXTRACT(EPOCH FROM ended - started)::BIGINT AS seconds, ongoing FROM (
+            SELECT new_orientation, flipped_at AS started,
+                COALESCE(LEAD(flipped_at) OVER (ORDER BY flipped_at), NOW()) AS ended,
+                LEAD(flipped_at) OVER (ORDER BY flipped_at) IS NULL AS ongoing
+            FROM flip_events WHERE guild_id = $1 AND old_orientation <> new_orientation
+        ) AS changes
+        WHERE new_orientation = 'Normal'
+        ORDER BY seconds DESC LIMIT 1",
+    )
+    .bind(guild.get() as i64)
+    .fetch_optional(db.pool())
+    .await?;
+
+    let last_moved = match last_flip {
+        Some(flip) => format!(
+            "<@{}> <t:{}:R>{}",
+            flip.user_id,
+            flip.flipped_at.timestamp(),
+            if flip.old_orientation == flip.new_orientation {
+                ", but it landed the same way up"
+            } else {
+                ""
+            }
+        ),
+        None => "Nobody, yet".to_owned(),
+    };
+
+    let in_state = match last_change {
+        Some((changed_at,)) => describe_duration(Utc::now() - changed_at),
+        None => "As long as anyone can remember".to_owned(),
+    };
+
+    let longest_upright = match longest_upright {
+        Some((seconds, ongoing)) => format!(
+            "{}{}",
+            describe_duration(Duration::seconds(seconds)),
+            if ongoing { " (and counting)" } else { "" }
+        ),
+        None => "None on record".to_owned(),
+    };
+
+    let occupants = read_occupants(db.pool(), guild).await?;
+    let sitting = if occupants.is_empty() {
+        "Nobody".to_owned()
+    } else {
+        occupants
+            .iter()
+            .map(|occupant| {
+                format!(
+                    "<@{}> since <t:{}:R>",
+                    occupant.user_id,
+                    occupant.sat_at.timestamp()
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    };
+
+    Ok(CreateEmbed::default()
+        .title("The Jouch")
+        .description(orientation.to_emotes())
+        .field("Currently", orientation.name(), true)
+        .field("For", in_state, true)
+        .field("Last moved by", last_moved, false)
+        .field("Longest upright streak", longest_upright, false)
+        .field("Sitting on it", sitting, false))
+}
+
+pub async fn jouch(ctx: &Context, command: &CommandInteraction) -> CommandResult {
+    let guild = command
+        .guild_id
+        .ok_or(anyhow!("Unable to get guild where command was sent"))?;
+    let options = command.data.options();
+    let subcommand = options
+        .first()
+        .ok_or(anyhow!("Please provide a valid subcommand"))?;
+
+    match (subcommand.name, &subcommand.value) {
+        ("status", ResolvedValue::SubCommand(_)) => {
+            let embed = {
+                let data = ctx.data.read().await;
+                let db = data.get::<Db>().ok_or(anyhow!("Unable to get database"))?;
+                status(db, guild).await?
+            };
+
+            command
+                .edit_response(&ctx, EditInteractionResponse::new().add_embed(embed))
+                .await?;
+
+            Ok(())
+        }
+        ("musicalchairs", ResolvedValue::SubCommand(_)) => {
+            musical_chairs(ctx, command, guild).await
+        }
+        _ => Err(anyhow!("Unknown option {}", subcommand.name)),
+    }
+}
@@ -1,6 +1,19 @@
+pub mod accessory;
+pub mod achievements;
 pub mod autonick;
 pub mod birthday;
 pub mod clear;
+pub mod consent;
+pub mod cooldown;
 pub mod db_migration;
+pub mod flip_odds;
+pub mod global_rankings;
+pub mod jouch;
+pub mod musical_chairs;
+pub mod names;
 pub mod novena;
+pub mod reaction_sits;
+pub mod seats;
 pub mod sit;
+pub mod skin;
+pub mod timezone;
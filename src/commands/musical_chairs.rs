@@ -0,0 +1,346 @@
+use anyhow::anyhow;
+use chrono::Utc;
+use rand::Rng;
+use serenity::all::{
+    ButtonStyle, CommandInteraction, Context, CreateActionRow, CreateButton,
+    CreateInteractionResponse, CreateInteractionResponseFollowup, CreateInteractionResponseMessage,
+    EditInteractionResponse, GuildId, MessageBuilder, User,
+};
+use serenity::futures::StreamExt;
+use std::time::Duration;
+use tracing::warn;
+
+use super::seats::sit_down;
+use super::sit::SitRefused;
+use crate::db::{Db, UserKey};
+use crate::CommandResult;
+
+// How long the lobby stays open if the host doesn't start the game first.
+const LOBBY_TIMEOUT: Duration = Duration::from_secs(60);
+// How long players have to grab a seat once the music stops.
+const ROUND_TIMEOUT: Duration = Duration::from_secs(10);
+// How long the music plays for, in milliseconds; random so nobody can time it.
+const MUSIC_MILLIS: std::ops::Range<u64> = 2000..6000;
+
+const MIN_PLAYERS: usize = 2;
+const MAX_PLAYERS: usize = 25;
+
+fn lobby_message(host: &User, players: &[User], deadline: i64) -> String {
+    let mut builder = MessageBuilder::new();
+    builder
+        .push("\u{1F3B6} ")
+        .mention(host)
+        .push(format!(
+            " is starting a game of musical chairs on The Jouch! It starts <t:{deadline}:R>, or when they start it.\nPlayers ({}/{MAX_PLAYERS}):",
+            players.len()
+        ));
+    for player in players {
+        builder.push("\n- ").mention(player);
+    }
+    builder.build()
+}
+
+fn lobby_buttons() -> Vec<CreateActionRow> {
+    vec![CreateActionRow::Buttons(vec![
+        CreateButton::new("chairs_join")
+            .style(ButtonStyle::Primary)
+            .label("Join"),
+        CreateButton::new("chairs_start")
+            .style(ButtonStyle::Success)
+            .label("Start"),
+    ])]
+}
+
+fn round_message(round: usize, players: &[User], seated: &[User], out: &[User]) -> String {
+    let mut builder = MessageBuilder::new();
+    for player in out {
+        builder.push("\u{274C} ").mention(player).push(" is out!\n");
+    }
+    builder.push(format!(
+        "**Round {round}**: {} seats for {} players.",
+        players.len() - 1,
+        players.len()
+    ));
+    for player in players {
+        builder
+            .push(if seated.iter().any(|seated| seated.id == player.id) {
+                "\n\u{1F6CB}\u{FE0F} "
+            } else {
+                "\n\u{1F9CD} "
+            })
+            .mention(player);
+    }
+    builder.build()
+}
+
+fn sit_button() -> Vec<CreateActionRow> {
+    vec![CreateActionRow::Buttons(vec![CreateButton::new(
+        "chairs_sit",
+    )
+    .style(ButtonStyle::Success)
+    .label("Sit!")])]
+}
+
+async fn not_playing(
+    ctx: &Context,
+    interaction: &serenity::all::ComponentInteraction,
+    content: &str,
+) -> CommandResult {
+    interaction
+        .create_response(
+            &ctx,
+            CreateInteractionResponse::Message(
+                CreateInteractionResponseMessage::new()
+                    .content(content)
+                    .ephemeral(true),
+            ),
+        )
+        .await?;
+    Ok(())
+}
+
+// Gather up players until the lobby times out or the host starts the game.
+async fn lobby(ctx: &Context, command: &CommandInteraction) -> CommandResult<Vec<User>> {
+    let host = &command.user;
+    let mut players = vec![host.clone()];
+    let deadline = (Utc::now() + LOBBY_TIMEOUT).timestamp();
+
+    command
+        .edit_response(
+            &ctx,
+            EditInteractionResponse::new()
+                .content(lobby_message(host, &players, deadline))
+                .components(lobby_buttons()),
+        )
+        .await?;
+    let msg = command.get_response(&ctx).await?;
+    let mut interactions = msg
+        .await_component_interaction(ctx)
+        .timeout(LOBBY_TIMEOUT)
+        .stream();
+
+    while let Some(interaction) = interactions.next().await {
+        if interaction.data.custom_id == "chairs_start" {
+            if interaction.user.id != host.id {
+                not_playing(
+                    ctx,
+                    &interaction,
+                    "Only whoever set up the game can start it.",
+                )
+                .await?;
+                continue;
+            }
+            if players.len() < MIN_PLAYERS {
+                not_playing(ctx, &interaction, "Wait for someone else to join first!").await?;
+                continue;
+            }
+            interaction
+                .create_response(&ctx, CreateInteractionResponse::Acknowledge)
+                .await?;
+            break;
+        }
+
+        if players
+            .iter()
+            .any(|player| player.id == interaction.user.id)
+        {
+            not_playing(ctx, &interaction, "You're already in!").await?;
+            continue;
+        }
+        if players.len() >= MAX_PLAYERS {
+            not_playing(ctx, &interaction, "Sorry, the game's full.").await?;
+            continue;
+        }
+        players.push(interaction.user.clone());
+        interaction
+            .create_response(
+                &ctx,
+                CreateInteractionResponse::UpdateMessage(
+                    CreateInteractionResponseMessage::new()
+                        .content(lobby_message(host, &players, deadline)),
+                ),
+            )
+            .await?;
+    }
+
+    Ok(players)
+}
+
+// One round: the music plays for a bit, then whoever's quickest to sit stays in.
+// Returns the players who got a seat, in the order they sat down.
+async fn round(
+    ctx: &Context,
+    command: &CommandInteraction,
+    round: usize,
+    players: &[User],
+    out: &[User],
+) -> CommandResult<Vec<User>> {
+    command
+        .edit_response(
+            &ctx,
+            EditInteractionResponse::new()
+                .content(format!(
+                    "{}\n\u{1F3B6} The music's playing...",
+                    round_message(round, players, &[], out)
+                ))
+                .components(vec![]),
+        )
+        .await?;
+
+    let music = rand::rng().random_range(MUSIC_MILLIS);
+    tokio::time::sleep(Duration::from_millis(music)).await;
+
+    command
+        .edit_response(
+            &ctx,
+            EditInteractionResponse::new()
+                .content(format!(
+                    "{}\n\u{1F507} The music stopped!",
+                    round_message(round, players, &[], out)
+                ))
+                .components(sit_button()),
+        )
+        .await?;
+    let msg = command.get_response(&ctx).await?;
+    let mut interactions = msg
+        .await_component_interaction(ctx)
+        .timeout(ROUND_TIMEOUT)
+        .stream();
+
+    let seats = players.len() - 1;
+    let mut seated: Vec<User> = Vec::new();
+    while let Some(interaction) = interactions.next().await {
+        if !players
+            .iter()
+            .any(|player| player.id == interaction.user.id)
+        {
+            not_playing(ctx, &interaction, "You're not in this round.").await?;
+            continue;
+        }
+        if seated.iter().any(|seated| seated.id == interaction.user.id) {
+            interaction
+                .create_response(&ctx, CreateInteractionResponse::Acknowledge)
+                .await?;
+            continue;
+        }
+
+        seated.push(interaction.user.clone());
+        let full = seated.len() >= seats;
+        interaction
+            .create_response(
+                &ctx,
+                CreateInteractionResponse::UpdateMessage(
+                    CreateInteractionResponseMessage::new()
+                        .content(format!(
+                            "{}\n\u{1F507} The music stopped!",
+                            round_message(round, players, &seated, out)
+                        ))
+                        .components(if full { vec![] } else { sit_button() }),
+                ),
+            )
+            .await?;
+        if full {
+            break;
+        }
+    }
+
+    Ok(seated)
+}
+
+pub async fn musical_chairs(
+    ctx: &Context,
+    command: &CommandInteraction,
+    guild: GuildId,
+) -> CommandResult {
+    let mut players = lobby(ctx, command).await?;
+    if players.len() < MIN_PLAYERS {
+        command
+            .edit_response(
+                &ctx,
+                EditInteractionResponse::new()
+                    .content("Nobody else joined, so the game's off.")
+                    .components(vec![]),
+            )
+            .await?;
+        return Ok(());
+    }
+
+    let mut rounds = 0;
+    let mut out = Vec::new();
+    while players.len() > 1 {
+        rounds += 1;
+        let seated = round(ctx, command, rounds, &players, &out).await?;
+        if seated.is_empty() {
+            command
+                .edit_response(
+                    &ctx,
+                    EditInteractionResponse::new()
+                        .content("Nobody sat down, so nobody wins. \u{1F937}")
+                        .components(vec![]),
+                )
+                .await?;
+            return Ok(());
+        }
+        out = players
+            .iter()
+            .filter(|player| !seated.iter().any(|seated| seated.id == player.id))
+            .cloned()
+            .collect();
+        players = seated;
+    }
+
+    let winner = &players[0];
+    command
+        .edit_response(
+            &ctx,
+            EditInteractionResponse::new()
+                .content(
+                    MessageBuilder::new()
+                        .push("\u{1F3C6} ")
+                        .mention(winner)
+                        .push(format!(
+                            " won musical chairs after {rounds} round{}!",
+                            if rounds == 1 { "" } else { "s" }
+                        ))
+                        .build(),
+                )
+                .components(vec![]),
+        )
+        .await?;
+
+    let games_won = {
+        let data = ctx.data.read().await;
+        let db = data.get::<Db>().ok_or(anyhow!("Unable to get database"))?;
+        let key = UserKey {
+            user: winner.id.into(),
+            guild: guild.into(),
+        };
+        db.increment(&key, "games_won").await?.games_won
+    };
+
+    let mut content = vec![MessageBuilder::new()
+        .mention(winner)
+        .push(if games_won == 1 {
+            " has won their first game on The Jouch!".to_owned()
+        } else {
+            format!(" has won {games_won} games on The Jouch!")
+        })
+        .build()];
+    let mut followup = CreateInteractionResponseFollowup::new();
+
+    // the winner gets The Jouch all to themself, if it works out; the win counts either way.
+    match sit_down(ctx, winner, Some(guild), &[]).await {
+        Ok(outcome) => {
+            content.extend(outcome.announcements);
+            followup = followup.add_file(outcome.image);
+        }
+        Err(err) if err.is::<SitRefused>() => content.push(err.to_string()),
+        Err(err) => warn!("Unable to sit the musical chairs winner down: {err}"),
+    }
+
+    command
+        .create_followup(&ctx.http, followup.content(content.join("\n")))
+        .await?;
+
+    Ok(())
+}
@@ -0,0 +1,128 @@
+use serenity::all::{collect, ChunkGuildFilter, Context, Event, GuildId, UserId};
+use serenity::futures::StreamExt;
+use std::collections::HashMap;
+use std::time::Duration;
+use tracing::{debug, warn};
+
+// Shown in place of anyone who can't be found, e.g. because they've left or deleted their account.
+pub const UNKNOWN_USER: &str = "Unknown user";
+pub const UNKNOWN_GUILD: &str = "Unknown server";
+
+// How long to wait for Discord to send back members that weren't in the cache.
+const CHUNK_TIMEOUT: Duration = Duration::from_secs(5);
+
+// Names of users as they appear in the guild, for listing lots of them at once.
+// Comes from the cache where possible, with everyone else fetched in a single request.
+pub async fn member_names(
+    ctx: &Context,
+    guild: GuildId,
+    users: &[UserId],
+) -> HashMap<UserId, String> {
+    let mut names = HashMap::new();
+    if let Some(cached) = guild.to_guild_cached(&ctx.cache) {
+        for user in users {
+            if let Some(member) = cached.members.get(user) {
+                names.insert(*user, member.display_name().to_owned());
+            }
+        }
+    }
+
+    let missing: Vec<UserId> = users
+        .iter()
+        .filter(|user| !names.contains_key(user))
+        .copied()
+        .collect();
+    if !missing.is_empty() {
+        names.extend(fetch_members(ctx, guild, missing).await);
+    }
+
+    // whoever's left isn't in the guild any more, but the bot might still know them from somewhere else.
+    for user in users {
+        names.entry(*user).or_insert_with(|| {
+            ctx.cache
+                .user(*user)
+                .map(|user| user.display_name().to_owned())
+                .unwrap_or_else(|| UNKNOWN_USER.to_owned())
+        });
+    }
+
+    names
+}
+
+// Ask the gateway for the given members of a guild; the answer can come back split over several chunks.
+async fn fetch_members(
+    ctx: &Context,
+    guild: GuildId,
+    users: Vec<UserId>,
+) -> HashMap<UserId, String> {
+    let nonce = format!("names-{}", rand::random::<u32>());
+
+    // start listening before asking, so the answer can't be missed.
+    let expected = nonce.clone();
+    let mut chunks = Box::pin(collect(&ctx.shard, move |event| match event {
+        Event::GuildMembersChunk(chunk) if chunk.nonce.as_ref() == Some(&expected) => Some((
+            chunk.chunk_index,
+            chunk.chunk_count,
+            chunk
+                .members
+                .iter()
+                .map(|(id, member)| (*id, member.display_name().to_owned()))
+                .collect::<Vec<_>>(),
+        )),
+        _ => None,
+    }));
+
+    ctx.shard.chunk_guild(
+        guild,
+        None,
+        false,
+        ChunkGuildFilter::UserIds(users),
+        Some(nonce),
+    );
+
+    let mut names = HashMap::new();
+    let received = tokio::time::timeout(CHUNK_TIMEOUT, async {
+        while let Some((index, count, members)) = chunks.next().await {
+            names.extend(members);
+            if index + 1 >= count {
+                break;
+            }
+        }
+    })
+    .await;
+    if received.is_err() {
+        warn!("Timed out waiting for members of guild {guild}");
+    }
+
+    names
+}
+
+// Names of users who might not share a guild with whoever's asking, e.g. for the global leaderboard.
+pub async fn user_names(ctx: &Context, users: &[UserId]) -> HashMap<UserId, String> {
+    let mut names = HashMap::new();
+    for user in users {
+        // to_user checks the cache before asking Discord.
+        let name = match user.to_user(ctx).await {
+            Ok(user) => user.display_name().to_owned(),
+            Err(err) => {
+                debug!("Unable to get user {user}: {err}");
+                UNKNOWN_USER.to_owned()
+            }
+        };
+        names.insert(*user, name);
+    }
+    names
+}
+
+pub async fn guild_name(ctx: &Context, guild: GuildId) -> String {
+    if let Some(name) = guild.name(&ctx.cache) {
+        return name;
+    }
+    match guild.to_partial_guild(ctx).await {
+        Ok(partial) => partial.name,
+        Err(err) => {
+            debug!("Unable to get guild {guild}: {err}");
+            UNKNOWN_GUILD.to_owned()
+        }
+    }
+}
@@ -0,0 +1,166 @@
+use anyhow::{anyhow, bail};
+use serenity::all::{
+    CommandInteraction, Context, CreateMessage, EditInteractionResponse, GuildId, Message,
+    Reaction, ReactionType, ResolvedValue, User,
+};
+use std::convert::TryFrom;
+use tracing::debug;
+
+use super::consent::{ask_friends, AskIn};
+use super::cooldown::{check_user_cooldown, refund_user_cooldown};
+use super::seats::sit_down;
+use super::sit::SitRefused;
+use crate::db::{Db, GuildData};
+use crate::CommandResult;
+
+// 🛋️, for guilds that haven't picked their own.
+const DEFAULT_REACTION_EMOJI: &str = "\u{1F6CB}\u{FE0F}";
+
+fn reaction_emoji(guild_data: &GuildData) -> ReactionType {
+    guild_data
+        .reaction_emoji
+        .as_deref()
+        .and_then(|emoji| ReactionType::try_from(emoji).ok())
+        .unwrap_or_else(|| ReactionType::Unicode(DEFAULT_REACTION_EMOJI.to_owned()))
+}
+
+// Custom emojis can be renamed, and clients don't always send the variation selector on unicode ones.
+fn same_emoji(a: &ReactionType, b: &ReactionType) -> bool {
+    match (a, b) {
+        (ReactionType::Custom { id: a, .. }, ReactionType::Custom { id: b, .. }) => a == b,
+        (ReactionType::Unicode(a), ReactionType::Unicode(b)) => {
+            a.trim_end_matches('\u{FE0F}') == b.trim_end_matches('\u{FE0F}')
+        }
+        _ => false,
+    }
+}
+
+// Sit the reactor on The Jouch with whoever wrote the message, if that's turned on in the guild.
+pub async fn process(ctx: &Context, reaction: &Reaction) -> CommandResult {
+    let (Some(guild), Some(_)) = (reaction.guild_id, reaction.user_id) else {
+        return Ok(());
+    };
+
+    let guild_data = {
+        let data = ctx.data.read().await;
+        let db = data.get::<Db>().ok_or(anyhow!("Unable to get database"))?;
+        db.read_guild(guild).await?.unwrap_or_default()
+    };
+    if !guild_data.reaction_sits || !same_emoji(&reaction.emoji, &reaction_emoji(&guild_data)) {
+        return Ok(());
+    }
+
+    let user = reaction.user(ctx).await?;
+    if user.bot {
+        return Ok(());
+    }
+
+    // reacting is just another way to /sit, so it shares the cooldown; no reply, or re-reacting would spam one.
+    if let Some(remaining) = check_user_cooldown(ctx, "sit", guild, user.id).await? {
+        debug!(
+            "{} is on cooldown for reaction sits for {remaining}",
+            user.id
+        );
+        return Ok(());
+    }
+
+    let msg = reaction.message(ctx).await?;
+    let result = sit_with_author(ctx, guild, &user, &msg).await;
+    if result.is_err() {
+        refund_user_cooldown(ctx, "sit", guild, user.id).await;
+    }
+
+    match result {
+        // only worth telling everyone about if it's something like The Jouch being full.
+        Err(err) if err.is::<SitRefused>() => {
+            msg.channel_id
+                .send_message(
+                    &ctx,
+                    CreateMessage::new()
+                        .content(err.to_string())
+                        .reference_message(&msg),
+                )
+                .await?;
+            Ok(())
+        }
+        result => result,
+    }
+}
+
+async fn sit_with_author(
+    ctx: &Context,
+    guild: GuildId,
+    user: &User,
+    msg: &Message,
+) -> CommandResult {
+    // reacting to your own message just means sitting alone.
+    let friends: Vec<&User> = if msg.author.id == user.id || msg.author.bot {
+        vec![]
+    } else {
+        vec![&msg.author]
+    };
+
+    let (friends, mut notes) = ask_friends(ctx, AskIn::Reply(msg), user, &friends).await?;
+    let outcome = sit_down(ctx, user, Some(guild), &friends).await?;
+    notes.extend(outcome.announcements);
+
+    let mut reply = CreateMessage::new()
+        .add_file(outcome.image)
+        .reference_message(msg);
+    if !notes.is_empty() {
+        reply = reply.content(notes.join("\n"));
+    }
+    msg.channel_id.send_message(&ctx, reply).await?;
+
+    Ok(())
+}
+
+// Turn sitting by reaction on or off for the guild, and pick which emoji does it.
+pub async fn reaction_sits(ctx: &Context, command: &CommandInteraction) -> CommandResult {
+    let guild = command
+        .guild_id
+        .ok_or(anyhow!("Unable to get guild where command was sent"))?;
+    let options = command.data.options();
+    let subcommand = options
+        .first()
+        .ok_or(anyhow!("Please provide a valid subcommand"))?;
+
+    let data = ctx.data.read().await;
+    let db = data.get::<Db>().ok_or(anyhow!("Unable to get database"))?;
+
+    let content = match (subcommand.name, &subcommand.value) {
+        ("enable", ResolvedValue::SubCommand(args)) => {
+            let emoji = args.iter().find_map(|arg| match (arg.name, &arg.value) {
+                ("emoji", ResolvedValue::String(emoji)) => Some(emoji.trim()),
+                _ => None,
+            });
+            if let Some(emoji) = emoji {
+                let Ok(emoji) = ReactionType::try_from(emoji) else {
+                    bail!("{emoji} isn't an emoji The Jouch can use.");
+                };
+                if matches!(&emoji, ReactionType::Unicode(text) if text.chars().any(char::is_whitespace))
+                {
+                    bail!("Pick a single emoji to react with.");
+                }
+                db.update_guild(guild, "reaction_emoji", emoji.to_string())
+                    .await?;
+            }
+            let guild_data = db.update_guild(guild, "reaction_sits", true).await?;
+            format!(
+                "React to a message with {} to sit on The Jouch with whoever sent it.",
+                reaction_emoji(&guild_data)
+            )
+        }
+        ("disable", _) => {
+            db.update_guild(guild, "reaction_sits", false).await?;
+            "Reacting to messages no longer sits anyone on The Jouch.".to_owned()
+        }
+        _ => bail!("Unknown option {}", subcommand.name),
+    };
+
+    command
+        .edit_response(&ctx, EditInteractionResponse::new().content(content))
+        .await?;
+
+    Ok(())
+}
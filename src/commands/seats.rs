@@ -0,0 +1,313 @@
+use anyhow::anyhow;
+use chrono::{DateTime, Duration, Utc};
+use serenity::all::{
+    CommandInteraction, Context, EditInteractionResponse, GuildId, MessageBuilder, User, UserId,
+};
+use sqlx::{FromRow, PgExecutor};
+use tracing::{error, info};
+
+use super::jouch::describe_duration;
+use super::names::member_names;
+use super::sit::{sit_internal, SitOutcome, SitRefused, MAX_FRIENDS};
+use crate::db::Db;
+use crate::CommandResult;
+
+// Enough room for the biggest sit there can be.
+pub const JOUCH_SEATS: usize = MAX_FRIENDS + 1;
+
+// How long someone stays on The Jouch before getting up on their own.
+const SEAT_TIMEOUT: Duration = Duration::minutes(30);
+
+// How often to check for anyone who's been sitting too long.
+const CHECK_INTERVAL: std::time::Duration = std::time::Duration::from_secs(60);
+
+#[derive(Debug, Clone, FromRow)]
+pub struct Occupant {
+    pub user_id: i64,
+    pub sat_at: DateTime<Utc>,
+}
+
+impl Occupant {
+    // when they'll get up if nobody makes them first
+    pub fn stands_at(&self) -> DateTime<Utc> {
+        self.sat_at + SEAT_TIMEOUT
+    }
+}
+
+// Everyone on The Jouch in the guild right now, longest seated first.
+pub async fn read_occupants<'e>(
+    executor: impl PgExecutor<'e>,
+    guild: GuildId,
+) -> CommandResult<Vec<Occupant>> {
+    Ok(sqlx::query_as(
+        "SELECT user_id, sat_at FROM jouch_seats WHERE guild_id = $1 ORDER BY sat_at",
+    )
+    .bind(guild.get() as i64)
+    .fetch_all(executor)
+    .await?)
+}
+
+async fn add_time_seated<'e>(
+    executor: impl PgExecutor<'e>,
+    guild: GuildId,
+    user: UserId,
+    seconds: i64,
+) -> CommandResult {
+    sqlx::query(
+        "INSERT INTO users (guild_id, user_id, seconds_seated) VALUES ($1, $2, $3)
+            ON CONFLICT (guild_id, user_id) DO UPDATE SET seconds_seated = users.seconds_seated + EXCLUDED.seconds_seated",
+    )
+    .bind(guild.get() as i64)
+    .bind(user.get() as i64)
+    .bind(seconds)
+    .execute(executor)
+    .await?;
+    Ok(())
+}
+
+// anyone past the timeout just hasn't been got up yet, and shouldn't be counted for longer than it.
+fn time_seated(sat_at: DateTime<Utc>) -> Duration {
+    (Utc::now() - sat_at).min(SEAT_TIMEOUT)
+}
+
+// Get the user off The Jouch, counting the time they spent on it; gives how long that was, if they were on it.
+pub async fn stand_up(db: &Db, guild: GuildId, user: UserId) -> CommandResult<Option<Duration>> {
+    let sat_at: Option<(DateTime<Utc>,)> = sqlx::query_as(
+        "DELETE FROM jouch_seats WHERE guild_id = $1 AND user_id = $2 RETURNING sat_at",
+    )
+    .bind(guild.get() as i64)
+    .bind(user.get() as i64)
+    .fetch_optional(db.pool())
+    .await?;
+
+    let Some((sat_at,)) = sat_at else {
+        return Ok(None);
+    };
+    let seated = time_seated(sat_at);
+    add_time_seated(db.pool(), guild, user, seated.num_seconds()).await?;
+    Ok(Some(seated))
+}
+
+// Whoever's on The Jouch besides the given users, if there isn't room for all of them as well.
+fn hogs<'o>(occupants: &'o [Occupant], seated: &[&User]) -> Option<Vec<&'o Occupant>> {
+    let others: Vec<&Occupant> = occupants
+        .iter()
+        .filter(|occupant| {
+            !seated
+                .iter()
+                .any(|user| occupant.user_id == user.id.get() as i64)
+        })
+        .collect();
+    // nobody takes up two seats, even if they brought themselves along.
+    let mut ids: Vec<UserId> = seated.iter().map(|user| user.id).collect();
+    ids.sort();
+    ids.dedup();
+
+    (others.len() + ids.len() > JOUCH_SEATS).then_some(others)
+}
+
+async fn jouch_full(ctx: &Context, guild: GuildId, hogs: &[&Occupant]) -> anyhow::Error {
+    let ids: Vec<UserId> = hogs
+        .iter()
+        .map(|occupant| UserId::new(occupant.user_id as u64))
+        .collect();
+    let names = member_names(ctx, guild, &ids).await;
+
+    let mut builder = MessageBuilder::new();
+    builder.push("The Jouch is full! ");
+    for (i, id) in ids.iter().enumerate() {
+        if i > 0 {
+            builder.push(if i + 1 == ids.len() { " & " } else { ", " });
+        }
+        builder.push_bold_safe(&names[id]);
+    }
+    builder.push(format!(
+        " {} hogging it; someone has to `/stand`, or the first of them gets up <t:{}:R>.",
+        if ids.len() == 1 { "is" } else { "are" },
+        hogs.iter()
+            .map(|occupant| occupant.stands_at())
+            .min()
+            .unwrap_or_else(Utc::now)
+            .timestamp()
+    ));
+    SitRefused(builder.build()).into()
+}
+
+// Hold a seat for everyone not already on The Jouch, as long as there's room for all of them.
+// Gives who got a seat held for them, so they can be let go again if the sit doesn't work out.
+async fn reserve_seats(
+    ctx: &Context,
+    db: &Db,
+    guild: GuildId,
+    seated: &[&User],
+) -> CommandResult<Vec<UserId>> {
+    let mut tx = db.pool().begin().await?;
+    // one sit at a time per guild, so two at once can't both squeeze into the last seat.
+    sqlx::query("SELECT pg_advisory_xact_lock($1)")
+        .bind(guild.get() as i64)
+        .execute(&mut *tx)
+        .await?;
+
+    let occupants = read_occupants(&mut *tx, guild).await?;
+    if let Some(hogs) = hogs(&occupants, seated) {
+        tx.rollback().await?;
+        return Err(jouch_full(ctx, guild, &hogs).await);
+    }
+
+    let mut reserved: Vec<UserId> = seated
+        .iter()
+        .map(|user| user.id)
+        .filter(|id| {
+            !occupants
+                .iter()
+                .any(|occupant| occupant.user_id == id.get() as i64)
+        })
+        .collect();
+    reserved.sort();
+    reserved.dedup();
+    for id in &reserved {
+        sqlx::query("INSERT INTO jouch_seats (guild_id, user_id) VALUES ($1, $2)")
+            .bind(guild.get() as i64)
+            .bind(id.get() as i64)
+            .execute(&mut *tx)
+            .await?;
+    }
+
+    tx.commit().await?;
+    Ok(reserved)
+}
+
+async fn release_seats(db: &Db, guild: GuildId, reserved: &[UserId]) -> CommandResult {
+    let ids: Vec<i64> = reserved.iter().map(|id| id.get() as i64).collect();
+    sqlx::query("DELETE FROM jouch_seats WHERE guild_id = $1 AND user_id = ANY($2)")
+        .bind(guild.get() as i64)
+        .bind(ids)
+        .execute(db.pool())
+        .await?;
+    Ok(())
+}
+
+// Anyone who was already on The Jouch starts their time over, after being credited for what they've sat so far.
+async fn restart_seats(db: &Db, guild: GuildId, users: &[UserId]) -> CommandResult {
+    let mut tx = db.pool().begin().await?;
+    for user in users {
+        let sat_at: Option<(DateTime<Utc>,)> = sqlx::query_as(
+            "DELETE FROM jouch_seats WHERE guild_id = $1 AND user_id = $2 RETURNING sat_at",
+        )
+        .bind(guild.get() as i64)
+        .bind(user.get() as i64)
+        .fetch_optional(&mut *tx)
+        .await?;
+        if let Some((sat_at,)) = sat_at {
+            add_time_seated(&mut *tx, guild, *user, time_seated(sat_at).num_seconds()).await?;
+        }
+        // they're part of this sit, even if they got up while it was being drawn.
+        sqlx::query("INSERT INTO jouch_seats (guild_id, user_id) VALUES ($1, $2)")
+            .bind(guild.get() as i64)
+            .bind(user.get() as i64)
+            .execute(&mut *tx)
+            .await?;
+    }
+    tx.commit().await?;
+    Ok(())
+}
+
+// Sit on The Jouch, staying there afterwards if it's in a guild. Seats are held before anything gets counted,
+// so nothing is unless there's room, and they're let go again if the sit doesn't work out.
+pub async fn sit_down(
+    ctx: &Context,
+    user: &User,
+    guild: Option<GuildId>,
+    with: &[&User],
+) -> CommandResult<SitOutcome> {
+    let seated: Vec<&User> = std::iter::once(user).chain(with.iter().copied()).collect();
+
+    let reserved = if let Some(guild) = guild {
+        let data = ctx.data.read().await;
+        let db = data.get::<Db>().ok_or(anyhow!("Unable to get database"))?;
+        reserve_seats(ctx, db, guild, &seated).await?
+    } else {
+        vec![]
+    };
+
+    let outcome = sit_internal(ctx, user, guild, with).await;
+
+    if let Some(guild) = guild {
+        let data = ctx.data.read().await;
+        let db = data.get::<Db>().ok_or(anyhow!("Unable to get database"))?;
+        if outcome.is_ok() {
+            let mut already_seated: Vec<UserId> = seated
+                .iter()
+                .map(|user| user.id)
+                .filter(|id| !reserved.contains(id))
+                .collect();
+            already_seated.sort();
+            already_seated.dedup();
+            restart_seats(db, guild, &already_seated).await?;
+        } else if let Err(err) = release_seats(db, guild, &reserved).await {
+            error!("Unable to let go of seats held for a sit that didn't happen: {err}");
+        }
+    }
+
+    outcome
+}
+
+// Get up anyone who's been sitting longer than the timeout, counting them as sitting for exactly that long.
+async fn stand_up_expired(ctx: &Context) -> anyhow::Result<()> {
+    let data = ctx.data.read().await;
+    let db = data.get::<Db>().ok_or(anyhow!("Unable to get database"))?;
+
+    let result = sqlx::query(
+        "WITH stood AS (
+            DELETE FROM jouch_seats WHERE sat_at <= $1 RETURNING guild_id, user_id
+        )
+        INSERT INTO users (guild_id, user_id, seconds_seated) SELECT guild_id, user_id, $2 FROM stood
+            ON CONFLICT (guild_id, user_id) DO UPDATE SET seconds_seated = users.seconds_seated + EXCLUDED.seconds_seated",
+    )
+    .bind(Utc::now() - SEAT_TIMEOUT)
+    .bind(SEAT_TIMEOUT.num_seconds())
+    .execute(db.pool())
+    .await?;
+
+    if result.rows_affected() > 0 {
+        info!("{} user(s) got up off The Jouch", result.rows_affected());
+    }
+
+    Ok(())
+}
+
+// function to be spun off into its own thread to periodically get people up off The Jouch
+pub async fn check_seats_loop(ctx: Context) {
+    loop {
+        if let Err(err) = stand_up_expired(&ctx).await {
+            error!("error standing users up! {}", err);
+        }
+
+        tokio::time::sleep(CHECK_INTERVAL).await;
+    }
+}
+
+pub async fn stand(ctx: &Context, command: &CommandInteraction) -> CommandResult {
+    let guild = command
+        .guild_id
+        .ok_or(anyhow!("Unable to get guild where command was sent"))?;
+
+    let seated = {
+        let data = ctx.data.read().await;
+        let db = data.get::<Db>().ok_or(anyhow!("Unable to get database"))?;
+        stand_up(db, guild, command.user.id).await?
+    };
+
+    let content = match seated {
+        Some(seated) => format!(
+            "You got up off The Jouch after {}.",
+            describe_duration(seated)
+        ),
+        None => "You're not on The Jouch right now.".to_owned(),
+    };
+    command
+        .edit_response(&ctx, EditInteractionResponse::new().content(content))
+        .await?;
+
+    Ok(())
+}
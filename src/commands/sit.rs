@@ -1,154 +1,521 @@
+use super::achievements::{achievement_catalog, check_achievements, record_companions};
 use super::autonick::check_nick_user_key;
 use super::birthday::is_birthday_today;
-use crate::db::{Db, UserKey};
-use crate::{CommandResult, EnvItemsContainer};
+use super::consent::{ask_friends, AskIn};
+use super::flip_odds::read_flip_table;
+use super::jouch::{describe_duration, record_flip};
+use super::names::{guild_name, member_names, user_names};
+use super::seats::sit_down;
+use super::skin::{read_skin, read_skin_image};
+use super::timezone::guild_today;
+use crate::avatars::{default_avatar, AvatarCache};
+use crate::cache::ByteCache;
+use crate::db::{Db, UserData, UserKey};
+use crate::CommandResult;
 use anyhow::anyhow;
-use enum_utils::TryFromRepr;
-use image::ImageFormat;
-use image::{
-    error, imageops::FilterType, DynamicImage, GenericImage, GenericImageView, ImageResult, Pixel,
-};
-use rand::{self, distr::StandardUniform, prelude::Distribution, Rng};
-use serde::{Deserialize, Serialize};
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use enum_utils::{FromStr, TryFromRepr};
+use image::DynamicImage;
 use serenity::all::{
-    CommandInteraction, Context, CreateAttachment, CreateEmbed, CreateInteractionResponseFollowup,
-    EditInteractionResponse, GuildId, MessageBuilder, ResolvedTarget, ResolvedValue, User, UserId,
+    ButtonStyle, CommandInteraction, Context, CreateActionRow, CreateAttachment, CreateButton,
+    CreateEmbed, CreateEmbedFooter, CreateInteractionResponse, CreateInteractionResponseFollowup,
+    CreateInteractionResponseMessage, EditInteractionResponse, GuildId, MessageBuilder,
+    ResolvedTarget, ResolvedValue, User, UserId,
 };
+use serenity::futures::StreamExt;
+use serenity::prelude::TypeMapKey;
+use sqlx::{FromRow, Postgres, QueryBuilder};
 use std::convert::TryInto;
-use std::io::Cursor;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use the_jouch::flip_table::FlipTable;
+use the_jouch::render::{
+    card_layers, decode_animation, flip_layers, render_scene, sit_layers, Animation, CardEntry,
+    JouchAssets, Seat, CARD_AVATAR_SIZE, CARD_ROWS, FLIPPER_AVATAR_SIZE, FLIP_SCENE_SIZE,
+};
+use the_jouch::scenes::{avatar_sizes, Scene};
+use tracing::warn;
 
-const SIT_ONE: (u32, u32) = (385, 64);
-const SIT_WITH: (u32, u32, u32, u32) = (240, 64, 580, 64);
-const HAT_OFFSET: (u32, u32) = (48, 64);
+// used all over, so it's easier to keep importing it from here.
+pub use the_jouch::render::JouchOrientation;
 
-#[derive(Default, Clone, Copy, Debug, Serialize, Deserialize, sqlx::Type)]
-#[sqlx(type_name = "jouch_orientation")]
-pub enum JouchOrientation {
-    #[default]
-    Normal,
-    UpsideDown,
-    RotatedLeft,
-    RotatedRight,
-}
+// Finished sit images are usually a few hundred KB, animated ones a few MB.
+const MAX_RENDER_CACHE_BYTES: usize = 64 * 1024 * 1024;
 
-impl JouchOrientation {
-    pub fn to_emotes(&self) -> &str {
-        match self {
-            JouchOrientation::Normal => {
-                "<:jouchup1:1117080763565879397><:jouchup2:1117080764572520449>"
-            }
-            JouchOrientation::UpsideDown => {
-                "<:jouchdn1:1117080756309721139><:jouchdn2:1117080758612410401> "
-            }
-            JouchOrientation::RotatedLeft => {
-                "<:jouchl1:1117080760185270366>\n<:jouchl2:1117080761615519814>"
-            }
-            JouchOrientation::RotatedRight => {
-                "<:jouchr1:1117079201321861150>\n<:jouchr2:1117079202890530906>"
-            }
-        }
-    }
+// how many users to show on each page of /rankings, and how long its buttons keep working.
+const RANKINGS_PAGE_SIZE: i64 = 10;
+const RANKINGS_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(300);
+
+// how many friends can be brought along for a single sit.
+pub const MAX_FRIENDS: usize = 5;
+
+// The right-click menu entry for sitting with a user; it's /sit with them as the friend.
+pub const SIT_WITH_COMMAND: &str = "Sit on The Jouch with";
+
+// Days in a row on The Jouch worth announcing.
+const STREAK_MILESTONES: [i32; 8] = [3, 7, 14, 30, 50, 100, 200, 365];
+
+// How /flip & /rectify show the new orientation of The Jouch.
+#[derive(Eq, PartialEq, Debug, Clone, Copy, Default, FromStr)]
+#[enumeration(case_insensitive)]
+pub enum FlipStyle {
+    #[default]
+    Emotes,
+    // a still image of The Jouch in its new orientation
+    Image,
+    // The Jouch tumbling from its old orientation to the new one
+    Animated,
 }
 
-#[derive(TryFromRepr)]
+#[derive(Clone, Copy, TryFromRepr)]
 #[repr(u8)]
 pub enum RankSortBy {
     Default,
     Sits,
     Flips,
+    Streak,
+    TimeSeated,
+}
+
+// How far back /rankings counts; periods are rolling, so a week is the last 7 days.
+#[derive(Eq, PartialEq, Debug, Clone, Copy, TryFromRepr)]
+#[repr(u8)]
+pub enum RankPeriod {
+    All,
+    Day,
+    Week,
+    Month,
+    Year,
 }
 
-impl Distribution<JouchOrientation> for StandardUniform {
-    fn sample<R: Rng + ?Sized>(&self, rng: &mut R) -> JouchOrientation {
-        match rng.random_range(0..=3) {
-            1 => JouchOrientation::UpsideDown,
-            2 => JouchOrientation::RotatedLeft,
-            3 => JouchOrientation::RotatedRight,
-            _ => JouchOrientation::Normal,
+impl RankPeriod {
+    fn start(&self) -> Option<DateTime<Utc>> {
+        let days = match self {
+            RankPeriod::All => return None,
+            RankPeriod::Day => 1,
+            RankPeriod::Week => 7,
+            RankPeriod::Month => 30,
+            RankPeriod::Year => 365,
+        };
+        Some(Utc::now() - Duration::days(days))
+    }
+
+    fn describe(&self) -> &str {
+        match self {
+            RankPeriod::All => "all time",
+            RankPeriod::Day => "past day",
+            RankPeriod::Week => "past week",
+            RankPeriod::Month => "past month",
+            RankPeriod::Year => "past year",
         }
     }
 }
 
-async fn get_face(
-    ctx: &Context,
-    user: &User,
-    guild: Option<GuildId>,
-) -> CommandResult<DynamicImage> {
-    let buffer = reqwest::get(if let Some(guild) = guild {
-        guild.member(ctx, user.id).await?.face()
+// Which servers /rankings covers; global only includes servers that have opted in.
+#[derive(Eq, PartialEq, Debug, Clone, Copy, TryFromRepr)]
+#[repr(u8)]
+pub enum RankScope {
+    Server,
+    Global,
+}
+
+#[derive(Eq, PartialEq, Debug, Clone, Copy, sqlx::Type)]
+#[sqlx(type_name = "activity_kind")]
+pub enum ActivityKind {
+    Sit,
+    Flip,
+}
+
+// JouchAssets comes from the library, so it needs a separate key to be stored in the client's data.
+pub struct JouchAssetsKey;
+
+impl TypeMapKey for JouchAssetsKey {
+    type Value = Arc<JouchAssets>;
+}
+
+// Finished sit images, keyed by everything that goes into drawing one (see render_key), so repeat sits skip rendering.
+pub struct RenderCache(Mutex<ByteCache>);
+
+impl RenderCache {
+    pub fn new() -> Self {
+        Self(Mutex::new(ByteCache::new(MAX_RENDER_CACHE_BYTES)))
+    }
+
+    fn get(&self, key: &str) -> Option<Arc<[u8]>> {
+        self.0.lock().unwrap().get(key)
+    }
+
+    fn insert(&self, key: String, image: &[u8]) {
+        self.0.lock().unwrap().insert(key, image.into());
+    }
+}
+
+impl TypeMapKey for RenderCache {
+    type Value = Arc<RenderCache>;
+}
+
+// Work out which avatar to use for a user (their guild specific one if they have one),
+// giving the key it's cached under along with where to download it from.
+async fn face_source(ctx: &Context, user: &User, guild: Option<GuildId>) -> (String, String) {
+    // Not critical; if the member can't be found just use the user's global avatar.
+    let member = if let Some(guild) = guild {
+        guild.member(ctx, user.id).await.ok()
     } else {
-        user.face()
-    })
-    .await?
-    .bytes()
-    .await?;
+        None
+    };
 
-    Ok(if let Some(img) = webp::Decoder::new(&buffer).decode() {
-        img.to_image()
+    if let Some((hash, member)) = member
+        .as_ref()
+        .and_then(|member| member.avatar.map(|hash| (hash, member)))
+    {
+        (hash.to_string(), member.face())
+    } else if let Some(hash) = user.avatar {
+        (hash.to_string(), user.face())
     } else {
-        image::load_from_memory(&buffer)?
-    }
-    .resize(128, 128, FilterType::CatmullRom))
-}
-
-// basically stolen from copy_from, but with blending the source & target pixels rather than replacement & limiting to a circle.
-fn blend(
-    target: &mut DynamicImage,
-    source: &DynamicImage,
-    x: u32,
-    y: u32,
-    circle: bool,
-) -> ImageResult<()> {
-    // Do bounds checking here so we can use the non-bounds-checking
-    // functions to copy pixels.
-    if target.width() < source.width() + x || target.height() < source.height() + y {
-        return Err(error::ImageError::Parameter(
-            error::ParameterError::from_kind(error::ParameterErrorKind::DimensionMismatch),
-        ));
+        // default avatars have no hash, but the url is just as unique.
+        (user.face(), user.face())
     }
+}
 
-    let source_ctr = source.height() as f32 / 2.0;
-    let r_squared = source_ctr * source_ctr;
+// Discord's CDN has avatars at any power of two from 16 to 4096, so ask for the smallest one that covers the seat
+// rather than the 1024 the url comes with; that's a lot of frames to decode for an animated avatar.
+fn cdn_avatar_size(size: u32) -> u32 {
+    size.clamp(16, 4096).next_power_of_two()
+}
 
-    for k in 0..source.height() {
-        for i in 0..source.width() {
-            let coord = (i as f32 - source_ctr, k as f32 - source_ctr);
-            // Limit to circle since that's how Discord shows profile pictures
-            // TODO - could try to do some kind of anti-aliasing to this...
-            if !circle || (coord.0 * coord.0 + coord.1 * coord.1) < r_squared {
-                let mut out_pixel = target.get_pixel(i + x, k + y);
-                out_pixel.blend(&source.get_pixel(i, k));
-                target.put_pixel(i + x, k + y, out_pixel);
-            }
+// Fetch & decode an avatar through the avatar cache, resized to fit a seat.
+async fn fetch_face(ctx: &Context, key: &str, url: &str, size: u32) -> CommandResult<Animation> {
+    let cache = ctx
+        .data
+        .read()
+        .await
+        .get::<AvatarCache>()
+        .cloned()
+        .ok_or(anyhow!("Unable to get avatar cache"))?;
+
+    let cdn_size = cdn_avatar_size(size);
+    let url = format!(
+        "{}?size={cdn_size}",
+        url.split_once('?').map_or(url, |(base, _)| base)
+    );
+    // each size is a different download, so it's cached separately.
+    let key = format!("{key}_{cdn_size}");
+    let data = cache.get(&key, &url).await?;
+    // decoding & resizing every frame of an animated avatar takes a while, so keep it off the async threads.
+    let decoded =
+        tokio::task::spawn_blocking(move || decode_animation(&data).map(|face| face.resize(size)))
+            .await?;
+    if decoded.is_err() {
+        // a broken avatar would otherwise keep coming back from the cache.
+        cache.forget(&key).await;
+    }
+    decoded
+}
+
+// Falls back to a default avatar rather than failing the whole image if it can't be fetched or decoded.
+// Also says whether it's the real avatar, since a fallback shouldn't end up cached.
+async fn face_or_default(
+    ctx: &Context,
+    user: &User,
+    key: &str,
+    url: &str,
+    size: u32,
+) -> (Animation, bool) {
+    match fetch_face(ctx, key, url, size).await {
+        Ok(face) => (face, true),
+        Err(err) => {
+            warn!(
+                "Unable to get avatar for {}, using default: {:?}",
+                user.id, err
+            );
+            (
+                Animation::still(DynamicImage::ImageRgba8(default_avatar(user.id, size))),
+                false,
+            )
         }
     }
+}
+
+async fn get_face(ctx: &Context, user: &User, guild: Option<GuildId>) -> Animation {
+    let (key, url) = face_source(ctx, user, guild).await;
+    face_or_default(ctx, user, &key, &url, FLIPPER_AVATAR_SIZE)
+        .await
+        .0
+}
+
+pub async fn jouch_assets(ctx: &Context) -> CommandResult<Arc<JouchAssets>> {
+    ctx.data
+        .read()
+        .await
+        .get::<JouchAssetsKey>()
+        .cloned()
+        .ok_or(anyhow!("Unable to get assets"))
+}
+
+// What gets drawn on a seat, besides the scene itself.
+struct SeatKey {
+    // key of the avatar in the avatar cache
+    avatar: String,
+    // whether it's their birthday
+    hat: bool,
+    accessory: Option<String>,
+}
+
+// Everything that changes how a sit image looks: the orientation, the scenes used,
+// plus what's on each seat in order.
+fn render_key(orientation: JouchOrientation, panels: &[&Scene], seats: &[SeatKey]) -> String {
+    let panels: Vec<&str> = panels.iter().map(|scene| scene.name.as_str()).collect();
+    let seats: Vec<String> = seats
+        .iter()
+        .map(|seat| {
+            format!(
+                "{}{}{}",
+                seat.avatar,
+                if seat.hat { "+hat" } else { "" },
+                seat.accessory
+                    .as_ref()
+                    .map(|accessory| format!("+{accessory}"))
+                    .unwrap_or_default()
+            )
+        })
+        .collect();
+    format!("{orientation:?}/{}/{}", panels.join(","), seats.join(","))
+}
+
+async fn record_activity(db: &Db, key: &UserKey, kind: ActivityKind) -> CommandResult {
+    sqlx::query("INSERT INTO activity_events (guild_id, user_id, kind) VALUES ($1, $2, $3)")
+        .bind(key.guild)
+        .bind(key.user)
+        .bind(kind)
+        .execute(db.pool())
+        .await?;
     Ok(())
 }
 
-pub async fn increment_sit_counter(db: &Db, user: &User, guild: GuildId) -> CommandResult {
+pub async fn increment_sit_counter(
+    db: &Db,
+    user: &User,
+    guild: GuildId,
+) -> CommandResult<UserData> {
     let key = UserKey {
         user: user.id.into(),
         guild: guild.into(),
     };
-    db.increment(&key, "sit_count").await?;
+    let user_data = db.increment(&key, "sit_count").await?;
+    record_activity(db, &key, ActivityKind::Sit).await?;
 
-    Ok(())
+    Ok(user_data)
 }
 
-pub async fn increment_flip_counter(db: &Db, user: &User, guild: GuildId) -> CommandResult {
+pub async fn increment_flip_counter(
+    db: &Db,
+    user: &User,
+    guild: GuildId,
+) -> CommandResult<UserData> {
     let key = UserKey {
         user: user.id.into(),
         guild: guild.into(),
     };
-    db.increment(&key, "flip_count").await?;
+    let user_data = db.increment(&key, "flip_count").await?;
+    record_activity(db, &key, ActivityKind::Flip).await?;
 
-    Ok(())
+    Ok(user_data)
+}
+
+// Whose sits & flips to count: everyone in a guild, or one user in every guild.
+enum CountScope {
+    Guild(GuildId),
+    User(UserId),
+}
+
+// Sit & flip counts for everyone in scope over the period.
+// For all time, that's just the lifetime counts; otherwise they're totalled up from activity_events,
+// with only the ids & counts filled in.
+async fn read_counts(
+    db: &Db,
+    scope: CountScope,
+    period: RankPeriod,
+    filter: &str,
+) -> CommandResult<Vec<UserData>> {
+    let (column, id) = match scope {
+        CountScope::Guild(guild) => ("guild_id", guild.get() as i64),
+        CountScope::User(user) => ("user_id", user.get() as i64),
+    };
+
+    let Some(start) = period.start() else {
+        return match scope {
+            CountScope::Guild(guild) => db.read_users(guild, filter).await,
+            CountScope::User(user) => db.read_user_guilds(user, filter).await,
+        };
+    };
+
+    let counts: Vec<(i64, i64, i32, i32)> = sqlx::query_as(&format!(
+        "SELECT guild_id, user_id,
+            COUNT(*) FILTER (WHERE kind = 'Sit')::INT AS sit_count,
+            COUNT(*) FILTER (WHERE kind = 'Flip')::INT AS flip_count
+        FROM activity_events WHERE {column} = $1 AND happened_at >= $2 {filter}
+        GROUP BY guild_id, user_id"
+    ))
+    .bind(id)
+    .bind(start)
+    .fetch_all(db.pool())
+    .await?;
+
+    Ok(counts
+        .into_iter()
+        .map(|(guild, user, sit_count, flip_count)| UserData {
+            id: UserKey { guild, user },
+            sit_count,
+            flip_count,
+            ..Default::default()
+        })
+        .collect())
+}
+
+// A row of a guild's leaderboard; users with the same sort key share a rank, like a tie in a race.
+#[derive(FromRow)]
+struct RankedUser {
+    user_id: i64,
+    sit_count: i32,
+    flip_count: i32,
+    sit_streak: i32,
+    best_sit_streak: i32,
+    last_sit_day: Option<NaiveDate>,
+    seconds_seated: i64,
+    rank: i64,
+    // how many users share this rank
+    tied: i64,
+    // how many users are on the leaderboard at all
+    total: i64,
+}
+
+impl RankedUser {
+    fn position(&self) -> String {
+        if self.tied > 1 {
+            format!("#{} (tied)", self.rank)
+        } else {
+            format!("#{}", self.rank)
+        }
+    }
+
+    fn streak(&self, today: NaiveDate) -> i32 {
+        current_streak(
+            &UserData {
+                sit_streak: self.sit_streak,
+                last_sit_day: self.last_sit_day,
+                ..Default::default()
+            },
+            today,
+        )
+    }
+}
+
+// Everyone in the guild who's sat or flipped over the period, ranked; finish it off with a WHERE or ORDER BY.
+fn ranked_users_query<'q>(
+    guild: GuildId,
+    sort_by: RankSortBy,
+    period: RankPeriod,
+    today: NaiveDate,
+) -> QueryBuilder<'q, Postgres> {
+    let mut query = QueryBuilder::new(
+        "SELECT * FROM (
+            SELECT *, COUNT(*) OVER (PARTITION BY rank) AS tied, COUNT(*) OVER () AS total FROM (
+                SELECT *, RANK() OVER (ORDER BY ",
+    );
+    match sort_by {
+        RankSortBy::Default | RankSortBy::Sits => {
+            query.push("sit_count DESC, flip_count DESC");
+        }
+        RankSortBy::Flips => {
+            query.push("flip_count DESC, sit_count DESC");
+        }
+        RankSortBy::Streak => {
+            query
+                .push("CASE WHEN last_sit_day >= ")
+                .push_bind(today - Duration::days(1))
+                .push(" THEN sit_streak ELSE 0 END DESC, best_sit_streak DESC");
+        }
+        RankSortBy::TimeSeated => {
+            query.push("seconds_seated DESC, sit_count DESC");
+        }
+    }
+    query.push(") AS rank FROM (");
+
+    if let Some(start) = period.start() {
+        // streaks & time seated aren't tracked per period.
+        query
+            .push(
+                "SELECT user_id,
+                    COUNT(*) FILTER (WHERE kind = 'Sit')::INT AS sit_count,
+                    COUNT(*) FILTER (WHERE kind = 'Flip')::INT AS flip_count,
+                    0 AS sit_streak, 0 AS best_sit_streak, NULL::DATE AS last_sit_day,
+                    0::BIGINT AS seconds_seated
+                FROM activity_events WHERE guild_id = ",
+            )
+            .push_bind(guild.get() as i64)
+            .push(" AND happened_at >= ")
+            .push_bind(start)
+            .push(" GROUP BY user_id");
+    } else {
+        query
+            .push(
+                "SELECT user_id, sit_count, flip_count, sit_streak, best_sit_streak, last_sit_day,
+                    seconds_seated
+                FROM users WHERE guild_id = ",
+            )
+            .push_bind(guild.get() as i64);
+    }
+
+    query.push(
+        ") AS counts WHERE sit_count > 0 OR flip_count > 0
+            ) AS ranked
+        ) AS board ",
+    );
+    query
+}
+
+// Count a sit today towards the user's daily streak, giving the streak if it just reached a milestone.
+async fn update_streak(db: &Db, key: &UserKey, today: NaiveDate) -> CommandResult<Option<i32>> {
+    let (streak, previous_day): (i32, Option<NaiveDate>) = sqlx::query_as(
+        "UPDATE users SET sit_streak = streak.new,
+            best_sit_streak = GREATEST(best_sit_streak, streak.new),
+            last_sit_day = GREATEST(last_sit_day, $3)
+        FROM (
+            SELECT last_sit_day AS previous,
+                CASE WHEN last_sit_day >= $3 THEN sit_streak
+                    WHEN last_sit_day = $3 - 1 THEN sit_streak + 1
+                    ELSE 1 END AS new
+            FROM users WHERE guild_id = $1 AND user_id = $2
+        ) AS streak
+        WHERE guild_id = $1 AND user_id = $2
+        RETURNING users.sit_streak, streak.previous",
+    )
+    .bind(key.guild)
+    .bind(key.user)
+    .bind(today)
+    .fetch_one(db.pool())
+    .await?;
+
+    // only the first sit of the day moves the streak along.
+    let new_day = previous_day.is_none_or(|day| day < today);
+    Ok(Some(streak).filter(|streak| new_day && STREAK_MILESTONES.contains(streak)))
+}
+
+// The stored streak is as of the last day they sat, so it's only still going if that was today or yesterday.
+fn current_streak(user_data: &UserData, today: NaiveDate) -> i32 {
+    match user_data.last_sit_day {
+        Some(day) if day >= today - Duration::days(1) => user_data.sit_streak,
+        _ => 0,
+    }
 }
 
 struct RankingData {
     name: String,
     sit_count: i32,
     flip_count: i32,
+    streak: i32,
+    best_streak: i32,
+    seconds_seated: i64,
 }
 
 async fn sit_check(
@@ -157,6 +524,7 @@ async fn sit_check(
     guild: Option<GuildId>,
     users: &Vec<User>,
     sort_by: RankSortBy,
+    period: RankPeriod,
 ) -> CommandResult<CreateEmbed> {
     let data = ctx.data.read().await;
     let db = data.get::<Db>().ok_or(anyhow!("Unable to get database"))?;
@@ -165,89 +533,84 @@ async fn sit_check(
     let mut sit_data: Vec<RankingData> = Vec::new();
 
     let title = if let Some(guild) = guild {
-        if users.is_empty() {
-            let users = db
-                .read_users(
-                    guild,
-                    match sort_by {
-                        RankSortBy::Default | RankSortBy::Sits => {
-                            "ORDER BY sit_count DESC, flip_count DESC LIMIT 10"
-                        }
-                        RankSortBy::Flips => "ORDER BY flip_count DESC, sit_count DESC LIMIT 10",
-                    },
-                )
-                .await?;
+        let today = guild_today(db, guild).await?;
+        // Get the data for all specified users in a single query.
+        let user_query = users
+            .iter()
+            .map(|u| u.id.to_string())
+            .collect::<Vec<String>>()
+            .join(", ");
+        let users_data = read_counts(
+            db,
+            CountScope::Guild(guild),
+            period,
+            &format!("AND user_id IN ({user_query})"),
+        )
+        .await?;
+
+        let ids: Vec<UserId> = users.iter().map(|user| user.id).collect();
+        let names = member_names(ctx, guild, &ids).await;
 
-            for user_data in users {
-                let user: User = Into::<UserId>::into(user_data.id.user as u64)
-                    .to_user(ctx)
-                    .await?;
-                let name = user.nick_in(ctx, guild).await.unwrap_or(user.name);
+        for user in users {
+            let name = names
+                .get(&user.id)
+                .cloned()
+                .unwrap_or_else(|| user.display_name().to_owned());
+            if let Some(user_data) = users_data.iter().find(|u| user.id == u.id.user as u64) {
                 sit_data.push(RankingData {
                     name,
                     sit_count: user_data.sit_count,
                     flip_count: user_data.flip_count,
+                    streak: current_streak(user_data, today),
+                    best_streak: user_data.best_sit_streak,
+                    seconds_seated: user_data.seconds_seated,
+                });
+            } else {
+                // User not found in database; still include with 0 since they asked.
+                sit_data.push(RankingData {
+                    name,
+                    sit_count: 0,
+                    flip_count: 0,
+                    streak: 0,
+                    best_streak: 0,
+                    seconds_seated: 0,
                 });
             }
+        }
 
-            "Sit Leaderboard"
-        } else {
-            // Get the data for all specified users in a single query.
-            let user_query = users
-                .iter()
-                .map(|u| u.id.to_string())
-                .collect::<Vec<String>>()
-                .join(", ");
-            let users_data = db
-                .read_users(guild, &format!("AND user_id IN ({user_query})"))
-                .await?;
-
-            for user in users {
-                let name = user.nick_in(ctx, guild).await.unwrap_or(user.name.clone());
-                if let Some(user_data) = users_data.iter().find(|u| user.id == u.id.user as u64) {
-                    sit_data.push(RankingData {
-                        name,
-                        sit_count: user_data.sit_count,
-                        flip_count: user_data.flip_count,
-                    });
-                } else {
-                    // User not found in database; still include with 0 since they asked.
-                    sit_data.push(RankingData {
-                        name,
-                        sit_count: 0,
-                        flip_count: 0,
-                    });
-                }
+        match sort_by {
+            RankSortBy::Default => { /* leave in the order users were passed to the function */ }
+            RankSortBy::Sits => {
+                sit_data.sort_by(|a, b| b.sit_count.cmp(&a.sit_count));
             }
-
-            match sort_by {
-                RankSortBy::Default => { /* leave in the order users were passed to the function */
-                }
-                RankSortBy::Sits => {
-                    sit_data.sort_by(|a, b| b.sit_count.cmp(&a.sit_count));
-                }
-                RankSortBy::Flips => {
-                    sit_data.sort_by(|a, b| b.flip_count.cmp(&a.flip_count));
-                }
+            RankSortBy::Flips => {
+                sit_data.sort_by(|a, b| b.flip_count.cmp(&a.flip_count));
+            }
+            RankSortBy::Streak => {
+                sit_data.sort_by_key(|d| std::cmp::Reverse((d.streak, d.best_streak)));
+            }
+            RankSortBy::TimeSeated => {
+                sit_data.sort_by_key(|d| std::cmp::Reverse(d.seconds_seated));
             }
-
-            "Sit Data For Users"
         }
+
+        "Sit Data For Users"
     } else {
         // get any guild that this user has data in.
-        let data_in_guilds = db.read_user_guilds(user.id, "").await?;
+        let data_in_guilds = read_counts(db, CountScope::User(user.id), period, "").await?;
 
         for data_in_guild in data_in_guilds {
             let guild = GuildId::new(data_in_guild.id.guild as u64);
-            let name = if let Some(name) = guild.name(&ctx.cache) {
-                name
-            } else {
-                guild.to_partial_guild(&ctx).await?.name
-            };
+            let name = guild_name(ctx, guild).await;
+            // every guild has its own idea of what day it is.
+            let today = guild_today(db, guild).await?;
             sit_data.push(RankingData {
                 name,
                 sit_count: data_in_guild.sit_count,
                 flip_count: data_in_guild.flip_count,
+                streak: current_streak(&data_in_guild, today),
+                best_streak: data_in_guild.best_sit_streak,
+                seconds_seated: data_in_guild.seconds_seated,
             });
         }
 
@@ -260,15 +623,347 @@ async fn sit_check(
             RankSortBy::Flips => {
                 sit_data.sort_by(|a, b| b.flip_count.cmp(&a.flip_count));
             }
+            RankSortBy::Streak => {
+                sit_data.sort_by_key(|d| std::cmp::Reverse((d.streak, d.best_streak)));
+            }
+            RankSortBy::TimeSeated => {
+                sit_data.sort_by_key(|d| std::cmp::Reverse(d.seconds_seated));
+            }
         }
 
         "Sit data in all servers"
     };
 
+    Ok(ranking_embed(
+        title,
+        sit_data,
+        !users.is_empty(),
+        sort_by,
+        period,
+    ))
+}
+
+// One page of a guild's full leaderboard, with where the user stands on it in the footer.
+// Also gives how many pages there are.
+async fn leaderboard_page(
+    ctx: &Context,
+    user: &User,
+    guild: GuildId,
+    sort_by: RankSortBy,
+    period: RankPeriod,
+    page: i64,
+) -> CommandResult<(CreateEmbed, i64)> {
+    let data = ctx.data.read().await;
+    let db = data.get::<Db>().ok_or(anyhow!("Unable to get database"))?;
+    let today = guild_today(db, guild).await?;
+
+    let mut query = ranked_users_query(guild, sort_by, period, today);
+    query
+        .push("ORDER BY rank, user_id LIMIT ")
+        .push_bind(RANKINGS_PAGE_SIZE)
+        .push(" OFFSET ")
+        .push_bind(page * RANKINGS_PAGE_SIZE);
+    let ranked: Vec<RankedUser> = query.build_query_as().fetch_all(db.pool()).await?;
+
+    let mut query = ranked_users_query(guild, sort_by, period, today);
+    query
+        .push("WHERE user_id = ")
+        .push_bind(user.id.get() as i64);
+    let own: Option<RankedUser> = query.build_query_as().fetch_optional(db.pool()).await?;
+
+    let total = ranked
+        .first()
+        .or(own.as_ref())
+        .map(|ranked| ranked.total)
+        .unwrap_or_default();
+    let pages = ((total + RANKINGS_PAGE_SIZE - 1) / RANKINGS_PAGE_SIZE).max(1);
+
+    let ids: Vec<UserId> = ranked
+        .iter()
+        .map(|ranked_user| UserId::new(ranked_user.user_id as u64))
+        .collect();
+    let names = member_names(ctx, guild, &ids).await;
+
+    let mut sit_data = Vec::new();
+    for (ranked_user, id) in ranked.iter().zip(&ids) {
+        sit_data.push(RankingData {
+            name: format!("{} {}", ranked_user.position(), names[id]),
+            sit_count: ranked_user.sit_count,
+            flip_count: ranked_user.flip_count,
+            streak: ranked_user.streak(today),
+            best_streak: ranked_user.best_sit_streak,
+            seconds_seated: ranked_user.seconds_seated,
+        });
+    }
+
+    let mut footer = format!("Page {} of {pages}", page + 1);
+    if let Some(own) = own {
+        footer += &format!(" \u{2022} You are {} of {}", own.position(), own.total);
+    } else {
+        footer += " \u{2022} You're not on this leaderboard yet";
+    }
+
+    let embed = ranking_embed("Sit Leaderboard", sit_data, false, sort_by, period)
+        .footer(CreateEmbedFooter::new(footer));
+
+    Ok((embed, pages))
+}
+
+// The avatar to show on the rankings card, or a stand-in for anyone who can't be found any more.
+async fn card_face(ctx: &Context, user: UserId, guild: GuildId) -> Animation {
+    match user.to_user(ctx).await {
+        Ok(user) => {
+            let (key, url) = face_source(ctx, &user, Some(guild)).await;
+            face_or_default(ctx, &user, &key, &url, CARD_AVATAR_SIZE)
+                .await
+                .0
+        }
+        Err(err) => {
+            warn!("Unable to get user {user} for the rankings card: {err}");
+            Animation::still(DynamicImage::ImageRgba8(default_avatar(
+                user,
+                CARD_AVATAR_SIZE,
+            )))
+        }
+    }
+}
+
+// The top of the guild's leaderboard drawn as an image, under The Jouch as it is right now.
+async fn leaderboard_card(
+    ctx: &Context,
+    guild: GuildId,
+    sort_by: RankSortBy,
+    period: RankPeriod,
+) -> CommandResult<CreateAttachment> {
+    let assets = jouch_assets(ctx).await?;
+    let orientation = read_orientation(ctx, Some(guild)).await?;
+
+    let (today, ranked) = {
+        let data = ctx.data.read().await;
+        let db = data.get::<Db>().ok_or(anyhow!("Unable to get database"))?;
+        let today = guild_today(db, guild).await?;
+        let mut query = ranked_users_query(guild, sort_by, period, today);
+        query
+            .push("ORDER BY rank, user_id LIMIT ")
+            .push_bind(CARD_ROWS as i64);
+        let ranked: Vec<RankedUser> = query.build_query_as().fetch_all(db.pool()).await?;
+        (today, ranked)
+    };
+
+    let mut entries = Vec::new();
+    for ranked_user in &ranked {
+        entries.push(CardEntry {
+            face: card_face(ctx, UserId::new(ranked_user.user_id as u64), guild).await,
+            rank: ranked_user.rank,
+            count: match sort_by {
+                RankSortBy::Default | RankSortBy::Sits => ranked_user.sit_count,
+                RankSortBy::Flips => ranked_user.flip_count,
+                RankSortBy::Streak => ranked_user.streak(today),
+                // whole minutes; the card has no room for units.
+                RankSortBy::TimeSeated => (ranked_user.seconds_seated / 60) as i32,
+            },
+        });
+    }
+
+    let couch = assets.get(&assets.scenes.empty_couch)?.frame_at(0).clone();
+    let flips = matches!(sort_by, RankSortBy::Flips);
+    let (image_bytes, format) = tokio::task::spawn_blocking(move || {
+        let (layers, width, height) = card_layers(&couch, orientation, entries, flips);
+        render_scene(&layers, width, height, JouchOrientation::Normal)
+    })
+    .await??;
+
+    Ok(CreateAttachment::bytes(
+        image_bytes,
+        format!("rankings.{}", format.extensions_str()[0]),
+    ))
+}
+
+fn page_buttons(page: i64, pages: i64) -> Vec<CreateActionRow> {
+    vec![CreateActionRow::Buttons(vec![
+        CreateButton::new("rankings_prev")
+            .style(ButtonStyle::Secondary)
+            .label("Prev")
+            .disabled(page <= 0),
+        CreateButton::new("rankings_next")
+            .style(ButtonStyle::Secondary)
+            .label("Next")
+            .disabled(page + 1 >= pages),
+    ])]
+}
+
+// Show the guild's leaderboard a page at a time, until the user stops paging through it.
+async fn leaderboard(
+    ctx: &Context,
+    command: &CommandInteraction,
+    guild: GuildId,
+    sort_by: RankSortBy,
+    period: RankPeriod,
+    card: bool,
+) -> CommandResult {
+    let mut page = 0;
+    let (embed, mut pages) =
+        leaderboard_page(ctx, &command.user, guild, sort_by, period, page).await?;
+    let mut response = EditInteractionResponse::new()
+        .add_embed(embed)
+        .components(if pages > 1 {
+            page_buttons(page, pages)
+        } else {
+            vec![]
+        });
+    if card {
+        response = response.new_attachment(leaderboard_card(ctx, guild, sort_by, period).await?);
+    }
+    command.edit_response(&ctx, response).await?;
+    if pages <= 1 {
+        return Ok(());
+    }
+
+    let msg = command.get_response(&ctx).await?;
+    let mut interactions = msg
+        .await_component_interaction(ctx)
+        .author_id(command.user.id)
+        .timeout(RANKINGS_TIMEOUT)
+        .stream();
+
+    while let Some(interaction) = interactions.next().await {
+        page = match interaction.data.custom_id.as_str() {
+            "rankings_prev" => page - 1,
+            _ => page + 1,
+        }
+        .clamp(0, pages - 1);
+
+        // the leaderboard may have changed since the last page, so the number of pages can too.
+        let (embed, new_pages) =
+            leaderboard_page(ctx, &command.user, guild, sort_by, period, page).await?;
+        pages = new_pages;
+        interaction
+            .create_response(
+                &ctx,
+                CreateInteractionResponse::UpdateMessage(
+                    CreateInteractionResponseMessage::new()
+                        .embed(embed)
+                        .components(page_buttons(page, pages)),
+                ),
+            )
+            .await?;
+    }
+
+    // nobody's paging any more, so the buttons wouldn't do anything.
+    command
+        .edit_response(&ctx, EditInteractionResponse::new().components(vec![]))
+        .await?;
+
+    Ok(())
+}
+
+// Top users & guilds across every guild that's opted in to global rankings, leaving out anyone who's opted out.
+async fn global_check(
+    ctx: &Context,
+    sort_by: RankSortBy,
+    period: RankPeriod,
+) -> CommandResult<Vec<CreateEmbed>> {
+    let data = ctx.data.read().await;
+    let db = data.get::<Db>().ok_or(anyhow!("Unable to get database"))?;
+
+    let order = match sort_by {
+        RankSortBy::Default | RankSortBy::Sits => "ORDER BY sit_count DESC, flip_count DESC",
+        RankSortBy::Flips => "ORDER BY flip_count DESC, sit_count DESC",
+        RankSortBy::Streak => {
+            return Err(anyhow!(
+                "Streaks are counted in each server's own time zone, so they can't be compared globally."
+            ))
+        }
+        RankSortBy::TimeSeated => {
+            return Err(anyhow!(
+                "Time on The Jouch is only ranked within each server."
+            ))
+        }
+    };
+
+    let user_counts = read_global_counts(db, "user_id", period, order).await?;
+    let ids: Vec<UserId> = user_counts
+        .iter()
+        .map(|(user, _, _)| UserId::new(*user as u64))
+        .collect();
+    let names = user_names(ctx, &ids).await;
+
+    let mut users = Vec::new();
+    for ((_, sit_count, flip_count), id) in user_counts.into_iter().zip(&ids) {
+        users.push(RankingData {
+            name: names[id].clone(),
+            sit_count,
+            flip_count,
+            streak: 0,
+            best_streak: 0,
+            seconds_seated: 0,
+        });
+    }
+
+    let mut guilds = Vec::new();
+    for (guild, sit_count, flip_count) in read_global_counts(db, "guild_id", period, order).await? {
+        guilds.push(RankingData {
+            name: guild_name(ctx, GuildId::new(guild as u64)).await,
+            sit_count,
+            flip_count,
+            streak: 0,
+            best_streak: 0,
+            seconds_seated: 0,
+        });
+    }
+
+    Ok(vec![
+        ranking_embed("Global Sit Leaderboard", users, false, sort_by, period),
+        ranking_embed("Top Servers", guilds, false, sort_by, period),
+    ])
+}
+
+// (id, sit_count, flip_count) totalled up by user_id or guild_id over every guild on the global leaderboard.
+async fn read_global_counts(
+    db: &Db,
+    group_by: &str,
+    period: RankPeriod,
+    order: &str,
+) -> CommandResult<Vec<(i64, i32, i32)>> {
+    let start = period.start();
+    let counts = if start.is_some() {
+        "SELECT guild_id, user_id,
+            COUNT(*) FILTER (WHERE kind = 'Sit') AS sit_count,
+            COUNT(*) FILTER (WHERE kind = 'Flip') AS flip_count
+        FROM activity_events WHERE happened_at >= $1 GROUP BY guild_id, user_id"
+    } else {
+        "SELECT guild_id, user_id, sit_count, flip_count FROM users"
+    };
+
+    let query = format!(
+        "SELECT counts.{group_by}, SUM(sit_count)::INT AS sit_count, SUM(flip_count)::INT AS flip_count
+        FROM ({counts}) AS counts JOIN guilds ON guilds.id = counts.guild_id
+        WHERE guilds.global_rankings AND NOT EXISTS (
+            SELECT FROM user_settings
+            WHERE user_settings.user_id = counts.user_id AND user_settings.hide_from_global
+        )
+        GROUP BY counts.{group_by} {order} LIMIT 10"
+    );
+    let mut query = sqlx::query_as(&query);
+    if let Some(start) = start {
+        query = query.bind(start);
+    }
+
+    Ok(query.fetch_all(db.pool()).await?)
+}
+
+// Lay out rankings as an embed; users asked for by name get shown even if they haven't done anything.
+fn ranking_embed(
+    title: &str,
+    sit_data: Vec<RankingData>,
+    named: bool,
+    sort_by: RankSortBy,
+    period: RankPeriod,
+) -> CreateEmbed {
     let mut embed = CreateEmbed::default();
 
     for data in sit_data {
-        if users.is_empty() && data.sit_count <= 0 && data.flip_count <= 0 {
+        if !named && data.sit_count <= 0 && data.flip_count <= 0 {
             // We have neither sit nor flip data and we weren't asked for by name, so don't display at all.
             continue;
         }
@@ -276,15 +971,36 @@ async fn sit_check(
 
         let sit_str = format!("Times on The Jouch: {}", data.sit_count);
         let flip_str = format!("Flips of The Jouch: {}", data.flip_count);
+        let streak_str = format!("Daily streak: {} (best {})", data.streak, data.best_streak);
+        let seated_str = format!(
+            "Time on The Jouch: {}",
+            describe_duration(Duration::seconds(data.seconds_seated))
+        );
+
+        // streaks aren't tracked per period, so only show them for all time.
+        let show_streak = period == RankPeriod::All
+            && (matches!(sort_by, RankSortBy::Streak) || (named && data.best_streak > 0));
+        if show_streak && matches!(sort_by, RankSortBy::Streak) {
+            msg.push_line(&streak_str);
+        }
+        // same goes for time seated.
+        let show_seated = period == RankPeriod::All
+            && (matches!(sort_by, RankSortBy::TimeSeated) || (named && data.seconds_seated > 0));
+        if show_seated && matches!(sort_by, RankSortBy::TimeSeated) {
+            msg.push_line(&seated_str);
+        }
 
         // if we were asked for by name, show both even if zero, but if we weren't, only show nonzero.
-        if !users.is_empty() || (data.sit_count > 0 && data.flip_count > 0) {
+        if named || (data.sit_count > 0 && data.flip_count > 0) {
             // makes sense to put the one that's being sorted by first.
             match sort_by {
                 RankSortBy::Flips => {
                     msg.push_line(flip_str).push_line(sit_str);
                 }
-                RankSortBy::Default | RankSortBy::Sits => {
+                RankSortBy::Default
+                | RankSortBy::Sits
+                | RankSortBy::Streak
+                | RankSortBy::TimeSeated => {
                     msg.push_line(sit_str).push_line(flip_str);
                 }
             };
@@ -293,204 +1009,302 @@ async fn sit_check(
         } else {
             msg.push_line(sit_str);
         }
+        if show_streak && !matches!(sort_by, RankSortBy::Streak) {
+            msg.push_line(streak_str);
+        }
+        if show_seated && !matches!(sort_by, RankSortBy::TimeSeated) {
+            msg.push_line(seated_str);
+        }
         embed = embed.field(data.name, msg.build(), false);
     }
-    embed = embed.title(title);
-
-    Ok(embed)
+    embed.title(if period == RankPeriod::All {
+        title.to_owned()
+    } else {
+        format!("{title} ({})", period.describe())
+    })
 }
 
-async fn sit_internal(
+async fn read_orientation(
     ctx: &Context,
-    user: &User,
     guild: Option<GuildId>,
-    with: Option<&User>,
-) -> CommandResult<Vec<u8>> {
-    let assets_dir = {
-        ctx.data
-            .read()
+) -> CommandResult<JouchOrientation> {
+    Ok(if let Some(guild) = guild {
+        let data = ctx.data.read().await;
+        let db = data.get::<Db>().ok_or(anyhow!("Unable to get database"))?;
+        // Not critical; don't raise an error if it isn't available.
+        db.read_guild(guild)
             .await
-            .get::<EnvItemsContainer>()
-            .ok_or(anyhow!("Unable to get config!"))?
-            .assets_dir
-            .clone()
-    };
-
-    let base_image_path = if with.is_some() {
-        assets_dir.join("jouch-0002.png")
+            .unwrap_or_default()
+            .map(|data| data.jouch_orientation)
+            .unwrap_or_default()
     } else {
-        assets_dir.join("jouch-0001.png")
-    };
+        Default::default()
+    })
+}
 
-    let mut base_image = image::ImageReader::open(base_image_path)?.decode()?;
+// A sit that couldn't happen, for a reason that's fine to tell everyone, unlike most errors.
+#[derive(Debug)]
+pub struct SitRefused(pub String);
 
-    let party_hat_image =
-        image::ImageReader::open(assets_dir.join("party-hat-0001.png"))?.decode()?;
+impl std::fmt::Display for SitRefused {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
 
-    let user_avatar = get_face(ctx, user, guild).await?;
+impl std::error::Error for SitRefused {}
 
-    if let Some(other) = with {
-        let with_avatar = get_face(ctx, other, guild).await?;
+// A finished sit: the image to post, and anything worth announcing along with it.
+pub struct SitOutcome {
+    pub image: CreateAttachment,
+    pub announcements: Vec<String>,
+}
 
-        blend(&mut base_image, &user_avatar, SIT_WITH.0, SIT_WITH.1, true)?;
-        blend(&mut base_image, &with_avatar, SIT_WITH.2, SIT_WITH.3, true)?;
+pub async fn sit_internal(
+    ctx: &Context,
+    user: &User,
+    guild: Option<GuildId>,
+    with: &[&User],
+) -> CommandResult<SitOutcome> {
+    let assets = jouch_assets(ctx).await?;
 
-        if let Some(guild) = guild {
-            if is_birthday_today(
-                ctx,
-                UserKey {
-                    user: user.id.into(),
-                    guild: guild.into(),
-                },
-            )
-            .await?
-            {
-                blend(
-                    &mut base_image,
-                    &party_hat_image,
-                    SIT_WITH.0 + HAT_OFFSET.0,
-                    SIT_WITH.1 - HAT_OFFSET.1,
-                    false,
-                )?;
-            }
-            if is_birthday_today(
-                ctx,
-                UserKey {
-                    user: user.id.into(),
-                    guild: guild.into(),
-                },
-            )
-            .await?
-            {
-                blend(
-                    &mut base_image,
-                    &party_hat_image,
-                    SIT_WITH.2 + HAT_OFFSET.0,
-                    SIT_WITH.3 - HAT_OFFSET.1,
-                    false,
-                )?;
-            }
-        }
-    } else {
-        blend(&mut base_image, &user_avatar, SIT_ONE.0, SIT_ONE.1, true)?;
-
-        if let Some(guild) = guild {
-            if is_birthday_today(
-                ctx,
-                UserKey {
-                    user: user.id.into(),
-                    guild: guild.into(),
-                },
-            )
-            .await?
-            {
-                blend(
-                    &mut base_image,
-                    &party_hat_image,
-                    SIT_ONE.0 + HAT_OFFSET.0,
-                    SIT_ONE.1 - HAT_OFFSET.1,
-                    false,
-                )?;
-            }
-        }
-    }
+    let seated: Vec<&User> = std::iter::once(user).chain(with.iter().copied()).collect();
 
-    // rotate output image based on current orientation in guild
-    let orientation = if let Some(guild) = guild {
+    // seasonal scenes follow the guild's own date, like streaks & birthdays do.
+    let (skin, today) = if let Some(guild) = guild {
         let data = ctx.data.read().await;
         let db = data.get::<Db>().ok_or(anyhow!("Unable to get database"))?;
-        // Not critical; don't raise an error if it isn't available.
-        db.read_guild(guild)
-            .await
-            .unwrap_or_default()
-            .map(|data| data.jouch_orientation)
-            .unwrap_or_default()
+        (read_skin(db, guild).await?, guild_today(db, guild).await?)
     } else {
-        Default::default()
+        (None, Utc::now().date_naive())
     };
-    base_image = match orientation {
-        JouchOrientation::Normal => base_image,
-        JouchOrientation::UpsideDown => base_image.rotate180(),
-        JouchOrientation::RotatedLeft => base_image.rotate270(),
-        JouchOrientation::RotatedRight => base_image.rotate90(),
+    let skin_scene = skin
+        .as_ref()
+        .map(|skin| skin.scene(assets.scenes.default_hat()));
+
+    let panels = assets
+        .scenes
+        .panels(seated.len(), today, skin_scene.as_ref(), &mut rand::rng());
+
+    // Work out what the image will look like before drawing anything, in case it's already been drawn.
+    let mut urls = Vec::new();
+    let mut seat_keys = Vec::new();
+    for seated_user in &seated {
+        let (avatar, url) = face_source(ctx, seated_user, guild).await;
+        let (hat, accessory) = if let Some(guild) = guild {
+            let key = UserKey {
+                user: seated_user.id.into(),
+                guild: guild.into(),
+            };
+            let accessory = {
+                let data = ctx.data.read().await;
+                let db = data.get::<Db>().ok_or(anyhow!("Unable to get database"))?;
+                db.read(&key).await?.and_then(|data| data.accessory)
+            };
+            (is_birthday_today(ctx, key).await?, accessory)
+        } else {
+            (false, None)
+        };
+        urls.push(url);
+        seat_keys.push(SeatKey {
+            avatar,
+            hat,
+            accessory,
+        });
+    }
+    // birthday hats go to whoever's sitting on their birthday, which is also worth an achievement.
+    let birthday_sits: Vec<bool> = seat_keys.iter().map(|seat_key| seat_key.hat).collect();
+
+    // rotate output image based on current orientation in guild
+    let orientation = read_orientation(ctx, guild).await?;
+
+    let render_cache = ctx
+        .data
+        .read()
+        .await
+        .get::<RenderCache>()
+        .cloned()
+        .ok_or(anyhow!("Unable to get render cache"))?;
+    let render_key = render_key(orientation, &panels, &seat_keys);
+    let (image_bytes, format) = if let Some(image_bytes) = render_cache.get(&render_key) {
+        let format = image::guess_format(&image_bytes)?;
+        (image_bytes.to_vec(), format)
+    } else {
+        // only read once it's known to be needed, since it's much bigger than the rest of the skin.
+        let mut skin_image: Option<Animation> = None;
+        let mut panel_images = Vec::new();
+        for scene in &panels {
+            let panel = match (guild, &skin_scene) {
+                (Some(guild), Some(skin_scene)) if skin_scene.name == scene.name => {
+                    if let Some(image) = &skin_image {
+                        image.clone()
+                    } else {
+                        let data = ctx.data.read().await;
+                        let db = data.get::<Db>().ok_or(anyhow!("Unable to get database"))?;
+                        let image = decode_animation(&read_skin_image(db, guild).await?)?;
+                        skin_image = Some(image.clone());
+                        image
+                    }
+                }
+                _ => assets.get(&scene.base_image)?.clone(),
+            };
+            panel_images.push((*scene, panel));
+        }
+
+        let mut complete = true;
+        let mut seats = Vec::new();
+        for (((seated_user, url), seat_key), avatar_size) in seated
+            .iter()
+            .zip(&urls)
+            .zip(seat_keys)
+            .zip(avatar_sizes(&panels))
+        {
+            let (face, found) =
+                face_or_default(ctx, seated_user, &seat_key.avatar, url, avatar_size).await;
+            complete &= found;
+            seats.push(Seat {
+                face,
+                hat: seat_key.hat,
+                accessory: seat_key.accessory,
+            });
+        }
+
+        let (layers, width, height) = sit_layers(&assets, panel_images, seats)?;
+
+        // Encoding every frame of an animation can take a while, so keep it off the async runtime.
+        let (image_bytes, format) =
+            tokio::task::spawn_blocking(move || render_scene(&layers, width, height, orientation))
+                .await??;
+
+        // don't hang on to stand-in avatars; next time the real one might be available.
+        if complete {
+            render_cache.insert(render_key, &image_bytes);
+        }
+
+        (image_bytes, format)
     };
 
-    let mut image_bytes: Vec<u8> = vec![];
-    base_image.write_to(&mut Cursor::new(&mut image_bytes), ImageFormat::Png)?;
+    let mut announcements = Vec::new();
 
     if let Some(guild) = guild {
+        let catalog = achievement_catalog(ctx).await?;
         let data = ctx.data.read().await;
         let db = data.get::<Db>().ok_or(anyhow!("Unable to get database"))?;
-        increment_sit_counter(db, user, guild).await?;
-        let _ = check_nick_user_key(
-            ctx,
-            &UserKey {
+        record_companions(db, guild, &seated).await?;
+        for (user, birthday_sit) in seated.iter().zip(birthday_sits) {
+            let key = UserKey {
                 user: user.id.into(),
                 guild: guild.into(),
-            },
-            db,
-        )
-        .await;
-        if let Some(user) = with {
+            };
             increment_sit_counter(db, user, guild).await?;
-            let _ = check_nick_user_key(
-                ctx,
-                &UserKey {
-                    user: user.id.into(),
-                    guild: guild.into(),
-                },
-                db,
-            )
-            .await;
+            if let Some(streak) = update_streak(db, &key, today).await? {
+                announcements.push(
+                    MessageBuilder::new()
+                        .push("\u{1F525} ")
+                        .mention(*user)
+                        .push(format!(" has sat on The Jouch {streak} days in a row!"))
+                        .build(),
+                );
+            }
+            // read back once the streak's been updated too, so achievements go by all of it.
+            let user_data = db.read(&key).await?.unwrap_or_default();
+            announcements.extend(
+                check_achievements(db, &catalog, user, &key, &user_data, birthday_sit).await?,
+            );
+            let _ = check_nick_user_key(ctx, &key, db).await;
         }
     }
 
-    Ok(image_bytes)
+    Ok(SitOutcome {
+        image: CreateAttachment::bytes(
+            image_bytes,
+            format!("jouch.{}", format.extensions_str()[0]),
+        ),
+        announcements,
+    })
+}
+
+// Draw a scene with the user in every seat, e.g. to preview a guild skin before it's saved.
+pub async fn preview_scene(
+    ctx: &Context,
+    user: &User,
+    guild: Option<GuildId>,
+    scene: &Scene,
+    base_image: &[u8],
+) -> CommandResult<CreateAttachment> {
+    let assets = jouch_assets(ctx).await?;
+    let base_image = decode_animation(base_image)?;
+
+    let (key, url) = face_source(ctx, user, guild).await;
+    let (face, _) = face_or_default(ctx, user, &key, &url, scene.avatar_size).await;
+
+    let seats = scene
+        .seats
+        .iter()
+        .map(|_| Seat {
+            face: face.clone(),
+            hat: false,
+            accessory: None,
+        })
+        .collect();
+    let (layers, width, height) = sit_layers(&assets, vec![(scene, base_image)], seats)?;
+
+    let (image_bytes, format) = tokio::task::spawn_blocking(move || {
+        render_scene(&layers, width, height, JouchOrientation::Normal)
+    })
+    .await??;
+
+    Ok(CreateAttachment::bytes(
+        image_bytes,
+        format!("jouch.{}", format.extensions_str()[0]),
+    ))
 }
 
 pub async fn sit(ctx: &Context, command: &CommandInteraction) -> CommandResult {
-    if let Some(user_arg) = command.data.options().first() {
-        if let ResolvedValue::User(user, _) = user_arg.value {
-            let image_bytes =
-                sit_internal(ctx, &command.user, command.guild_id, Some(user)).await?;
-
-            let files = CreateAttachment::bytes(image_bytes, "jouch.png");
-
-            // command.channel_id.send_files(&ctx.http, files, |m|{ m }).await?;
-            command
-                .create_followup(
-                    &ctx,
-                    CreateInteractionResponseFollowup::new().add_file(files),
-                )
-                .await?;
+    let mut friends: Vec<&User> = Vec::new();
 
-            Ok(())
+    if let Some(ResolvedTarget::User(user, _)) = command.data.target() {
+        friends.push(user);
+    }
+
+    for arg in command.data.options() {
+        if let ResolvedValue::User(user, _) = arg.value {
+            // don't give anyone two seats if they got listed more than once.
+            if !friends.iter().any(|friend| friend.id == user.id) {
+                friends.push(user);
+            }
         } else {
-            Err(anyhow!("Couldn't find your friend! (Argument invalid)"))
+            return Err(anyhow!("Couldn't find your friend! (Argument invalid)"));
         }
-    } else {
-        let image_bytes = sit_internal(ctx, &command.user, command.guild_id, None).await?;
-        let file = CreateAttachment::bytes(image_bytes, "jouch.png");
+    }
 
-        command
-            .create_followup(
-                &ctx.http,
-                CreateInteractionResponseFollowup::new().add_file(file),
-            )
-            .await?;
+    let (friends, mut notes) =
+        ask_friends(ctx, AskIn::Command(command), &command.user, &friends).await?;
+    let outcome = sit_down(ctx, &command.user, command.guild_id, &friends).await?;
+    notes.extend(outcome.announcements);
 
-        Ok(())
+    let mut followup = CreateInteractionResponseFollowup::new().add_file(outcome.image);
+    if !notes.is_empty() {
+        followup = followup.content(notes.join("\n"));
     }
+    command.create_followup(&ctx.http, followup).await?;
+
+    Ok(())
 }
 
 pub async fn rank(ctx: &Context, command: &CommandInteraction) -> CommandResult {
     let mut users = Vec::new();
 
     let mut sort_by = RankSortBy::Default;
+    let mut period = RankPeriod::All;
+    let mut scope = RankScope::Server;
+    let mut card = false;
 
     for arg in &command.data.options() {
         if let ResolvedValue::User(user, _) = arg.value {
             users.push(user.to_owned());
+        } else if let ("card", ResolvedValue::Boolean(value)) = (arg.name, &arg.value) {
+            card = *value;
         } else if let ResolvedValue::Integer(as_int) = arg.value {
             match arg.name {
                 "sort" => {
@@ -498,12 +1312,69 @@ pub async fn rank(ctx: &Context, command: &CommandInteraction) -> CommandResult
                         .try_into()
                         .map_err(|_| anyhow!("Invalid sort value passed!"))?
                 }
+                "period" => {
+                    period = (as_int as u8)
+                        .try_into()
+                        .map_err(|_| anyhow!("Invalid period value passed!"))?
+                }
+                "scope" => {
+                    scope = (as_int as u8)
+                        .try_into()
+                        .map_err(|_| anyhow!("Invalid scope value passed!"))?
+                }
                 _ => return Err(anyhow!("Unknown/unimplemented option {}", arg.name).into()),
             };
         }
     }
 
-    let embed = sit_check(ctx, &command.user, command.guild_id, &users, sort_by).await?;
+    if matches!(sort_by, RankSortBy::Streak) && period != RankPeriod::All {
+        return Err(anyhow!(
+            "Streaks always count up to today, so they can't be limited to a period."
+        ));
+    }
+    if matches!(sort_by, RankSortBy::TimeSeated) && period != RankPeriod::All {
+        return Err(anyhow!(
+            "Time on The Jouch is only kept as a running total, so it can't be limited to a period."
+        ));
+    }
+
+    if scope == RankScope::Global {
+        if card {
+            return Err(anyhow!(
+                "The card can only be drawn for a whole server's leaderboard."
+            ));
+        }
+        if !users.is_empty() {
+            return Err(anyhow!(
+                "The global leaderboard can't be narrowed down to particular users."
+            ));
+        }
+        let embeds = global_check(ctx, sort_by, period).await?;
+        command
+            .edit_response(&ctx, EditInteractionResponse::new().add_embeds(embeds))
+            .await?;
+        return Ok(());
+    }
+
+    if let (Some(guild), true) = (command.guild_id, users.is_empty()) {
+        return leaderboard(ctx, command, guild, sort_by, period, card).await;
+    }
+
+    if card {
+        return Err(anyhow!(
+            "The card can only be drawn for a whole server's leaderboard."
+        ));
+    }
+
+    let embed = sit_check(
+        ctx,
+        &command.user,
+        command.guild_id,
+        &users,
+        sort_by,
+        period,
+    )
+    .await?;
 
     command
         .edit_response(&ctx, EditInteractionResponse::new().add_embed(embed))
@@ -512,46 +1383,120 @@ pub async fn rank(ctx: &Context, command: &CommandInteraction) -> CommandResult
     Ok(())
 }
 
+// Render The Jouch in its new orientation for /flip & /rectify, with the user who did it next to it.
+async fn flip_image(
+    ctx: &Context,
+    user: &User,
+    guild: Option<GuildId>,
+    from: JouchOrientation,
+    to: JouchOrientation,
+    tumble: bool,
+) -> CommandResult<CreateAttachment> {
+    let assets = jouch_assets(ctx).await?;
+    let couch = assets.get(&assets.scenes.empty_couch)?.frame_at(0);
+    let layers = flip_layers(couch, get_face(ctx, user, guild).await, from, to, tumble);
+
+    let (image_bytes, format) = tokio::task::spawn_blocking(move || {
+        render_scene(
+            &layers,
+            FLIP_SCENE_SIZE.0,
+            FLIP_SCENE_SIZE.1,
+            JouchOrientation::Normal,
+        )
+    })
+    .await??;
+
+    Ok(CreateAttachment::bytes(
+        image_bytes,
+        format!("jouch.{}", format.extensions_str()[0]),
+    ))
+}
+
+fn flip_style(command: &CommandInteraction) -> CommandResult<FlipStyle> {
+    for arg in command.data.options() {
+        if let ("style", ResolvedValue::String(style)) = (arg.name, arg.value) {
+            return FlipStyle::from_str(style).map_err(|_| anyhow!("Invalid style value passed!"));
+        }
+    }
+    Ok(FlipStyle::default())
+}
+
+async fn flip_response(
+    ctx: &Context,
+    command: &CommandInteraction,
+    from: JouchOrientation,
+    to: JouchOrientation,
+    pose: &str,
+) -> CommandResult<EditInteractionResponse> {
+    let style = flip_style(command)?;
+
+    Ok(match style {
+        FlipStyle::Emotes => {
+            EditInteractionResponse::new().content(to.to_emotes().to_owned() + pose)
+        }
+        FlipStyle::Image | FlipStyle::Animated => {
+            EditInteractionResponse::new().content(pose).new_attachment(
+                flip_image(
+                    ctx,
+                    &command.user,
+                    command.guild_id,
+                    from,
+                    to,
+                    style == FlipStyle::Animated,
+                )
+                .await?,
+            )
+        }
+    })
+}
+
 pub async fn flip(ctx: &Context, command: &CommandInteraction) -> CommandResult {
-    // TODO - weight this to prefer orientations other than current (and potentially to add rare "orientations" in the future)
-    let new_orientation: JouchOrientation = rand::random();
+    let old_orientation = read_orientation(ctx, command.guild_id).await?;
+    let mut new_orientation = FlipTable::default().pick(old_orientation, &mut rand::rng());
+    let mut announcements = Vec::new();
 
     if let Some(guild) = command.guild_id {
+        let catalog = achievement_catalog(ctx).await?;
         let data = ctx.data.read().await;
         let db = data.get::<Db>().ok_or(anyhow!("Unable to get database"))?;
+        let key = UserKey {
+            user: command.user.id.into(),
+            guild: guild.into(),
+        };
+
+        new_orientation = read_flip_table(db, guild)
+            .await?
+            .pick(old_orientation, &mut rand::rng());
 
-        increment_flip_counter(db, &command.user, guild).await?;
+        let user_data = increment_flip_counter(db, &command.user, guild).await?;
 
         db.update_guild(guild, "jouch_orientation", new_orientation)
             .await?;
+        record_flip(db, guild, command.user.id, old_orientation, new_orientation).await?;
 
-        let _ = check_nick_user_key(
-            ctx,
-            &UserKey {
-                user: command.user.id.into(),
-                guild: guild.into(),
-            },
-            db,
-        )
-        .await;
-    }
+        announcements =
+            check_achievements(db, &catalog, &command.user, &key, &user_data, false).await?;
 
-    let emote = new_orientation.to_emotes().to_owned();
+        let _ = check_nick_user_key(ctx, &key, db).await;
+    }
 
     if let Some(ResolvedTarget::Message(ref msg)) = command.data.target() {
         let mut builder = MessageBuilder::new();
-        builder.push(emote);
+        builder.push(new_orientation.to_emotes());
         builder.push("︵╰(°□°╰) ← ");
         builder.mention(&command.user);
+        for announcement in &announcements {
+            builder.push("\n").push(announcement);
+        }
         msg.reply(ctx, builder.build()).await?;
         command.delete_response(&ctx).await?;
     } else {
-        command
-            .edit_response(
-                &ctx,
-                EditInteractionResponse::new().content(emote + "︵╰(°□°╰)"),
-            )
-            .await?;
+        let pose = std::iter::once("︵╰(°□°╰)".to_owned())
+            .chain(announcements)
+            .collect::<Vec<_>>()
+            .join("\n");
+        let response = flip_response(ctx, command, old_orientation, new_orientation, &pose).await?;
+        command.edit_response(&ctx, response).await?;
     }
 
     Ok(())
@@ -559,22 +1504,25 @@ pub async fn flip(ctx: &Context, command: &CommandInteraction) -> CommandResult
 
 pub async fn rectify(ctx: &Context, command: &CommandInteraction) -> CommandResult {
     let new_orientation: JouchOrientation = JouchOrientation::Normal;
+    let old_orientation = read_orientation(ctx, command.guild_id).await?;
 
     if let Some(guild) = command.guild_id {
         let data = ctx.data.read().await;
         let db = data.get::<Db>().ok_or(anyhow!("Unable to get database"))?;
         db.update_guild(guild, "jouch_orientation", new_orientation)
             .await?;
+        record_flip(db, guild, command.user.id, old_orientation, new_orientation).await?;
     }
 
-    let emote = new_orientation.to_emotes().to_owned();
-
-    command
-        .edit_response(
-            &ctx,
-            EditInteractionResponse::new().content(emote + "ノ( ˙ - ˙ ノ)"),
-        )
-        .await?;
+    let response = flip_response(
+        ctx,
+        command,
+        old_orientation,
+        new_orientation,
+        "ノ( ˙ - ˙ ノ)",
+    )
+    .await?;
+    command.edit_response(&ctx, response).await?;
 
     Ok(())
 }
@@ -0,0 +1,288 @@
+use anyhow::{anyhow, bail};
+use chrono::{DateTime, Utc};
+use serenity::all::{
+    Attachment, ButtonStyle, CommandInteraction, Context, CreateActionRow, CreateButton,
+    CreateInteractionResponse, CreateInteractionResponseMessage, EditInteractionResponse, GuildId,
+    ResolvedValue,
+};
+use sqlx::FromRow;
+use std::convert::TryFrom;
+use std::io::Cursor;
+use std::time::Duration;
+
+use super::sit::{jouch_assets, preview_scene, MAX_FRIENDS};
+use crate::db::Db;
+use crate::CommandResult;
+use the_jouch::scenes::{Hat, Scene};
+
+// Limits on custom skins, so they look reasonable next to the built in scenes & don't take forever to draw.
+const MIN_SKIN_SIZE: (u32, u32) = (320, 180);
+const MAX_SKIN_SIZE: (u32, u32) = (1920, 1080);
+const MAX_SKIN_BYTES: u32 = 8 * 1024 * 1024;
+const MIN_AVATAR_SIZE: u32 = 32;
+const MAX_AVATAR_SIZE: u32 = 512;
+const DEFAULT_AVATAR_SIZE: u32 = 128;
+// how long to wait for the preview to be saved or canceled.
+const CONFIRM_TIMEOUT: Duration = Duration::from_secs(300);
+
+// A guild's own image of The Jouch; the image itself is only read when it's actually needed.
+#[derive(Debug, Clone, FromRow)]
+pub struct GuildSkin {
+    pub guild_id: i64,
+    #[sqlx(json)]
+    pub seats: Vec<(u32, u32)>,
+    pub avatar_size: i32,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl GuildSkin {
+    // The skin as a scene to sit on. Its name changes whenever the skin does, so old renders aren't reused.
+    pub fn scene(&self, hat: Option<Hat>) -> Scene {
+        let name = format!(
+            "guild-{}-{}",
+            self.guild_id,
+            self.updated_at.timestamp_millis()
+        );
+        Scene {
+            base_image: name.clone(),
+            name,
+            seats: self.seats.clone(),
+            avatar_size: self.avatar_size as u32,
+            hat,
+            overlays: vec![],
+            active: None,
+        }
+    }
+}
+
+pub async fn read_skin(db: &Db, guild: GuildId) -> CommandResult<Option<GuildSkin>> {
+    Ok(sqlx::query_as(
+        "SELECT guild_id, seats, avatar_size, updated_at FROM guild_skins WHERE guild_id = $1",
+    )
+    .bind(guild.get() as i64)
+    .fetch_optional(db.pool())
+    .await?)
+}
+
+pub async fn read_skin_image(db: &Db, guild: GuildId) -> CommandResult<Vec<u8>> {
+    Ok(
+        sqlx::query_scalar("SELECT image FROM guild_skins WHERE guild_id = $1")
+            .bind(guild.get() as i64)
+            .fetch_one(db.pool())
+            .await?,
+    )
+}
+
+// Seats are given as "x,y" pairs separated by spaces, e.g. "240,64 580,64".
+fn parse_seats(seats: &str) -> CommandResult<Vec<(u32, u32)>> {
+    seats
+        .split_whitespace()
+        .map(|seat| {
+            let (x, y) = seat
+                .split_once(',')
+                .ok_or(anyhow!("Seat {seat} should be an x,y pair"))?;
+            Ok((
+                x.trim()
+                    .parse()
+                    .map_err(|_| anyhow!("Invalid x in {seat}"))?,
+                y.trim()
+                    .parse()
+                    .map_err(|_| anyhow!("Invalid y in {seat}"))?,
+            ))
+        })
+        .collect()
+}
+
+fn validate((width, height): (u32, u32), seats: &[(u32, u32)], avatar_size: u32) -> CommandResult {
+    if width < MIN_SKIN_SIZE.0
+        || height < MIN_SKIN_SIZE.1
+        || width > MAX_SKIN_SIZE.0
+        || height > MAX_SKIN_SIZE.1
+    {
+        bail!(
+            "The image is {width}x{height}, but needs to be between {}x{} and {}x{}.",
+            MIN_SKIN_SIZE.0,
+            MIN_SKIN_SIZE.1,
+            MAX_SKIN_SIZE.0,
+            MAX_SKIN_SIZE.1
+        );
+    }
+    if !(MIN_AVATAR_SIZE..=MAX_AVATAR_SIZE).contains(&avatar_size) {
+        bail!("Avatar size needs to be between {MIN_AVATAR_SIZE} and {MAX_AVATAR_SIZE}.");
+    }
+    if seats.is_empty() || seats.len() > MAX_FRIENDS + 1 {
+        bail!("There needs to be between 1 and {} seats.", MAX_FRIENDS + 1);
+    }
+    for (x, y) in seats {
+        if x.checked_add(avatar_size).is_none_or(|right| right > width)
+            || y.checked_add(avatar_size)
+                .is_none_or(|bottom| bottom > height)
+        {
+            bail!("The avatar at {x},{y} wouldn't fit in the image.");
+        }
+    }
+    Ok(())
+}
+
+async fn set_skin(
+    ctx: &Context,
+    command: &CommandInteraction,
+    guild: GuildId,
+    attachment: &Attachment,
+    seats: &str,
+    avatar_size: u32,
+) -> CommandResult {
+    if attachment.size > MAX_SKIN_BYTES {
+        bail!(
+            "The image is too big; it can be at most {}MB.",
+            MAX_SKIN_BYTES / 1024 / 1024
+        );
+    }
+
+    let seats = parse_seats(seats)?;
+    let image = attachment.download().await?;
+    let dimensions = image::ImageReader::new(Cursor::new(&image))
+        .with_guessed_format()?
+        .into_dimensions()
+        .map_err(|_| anyhow!("Unable to read the image; try a PNG, GIF or WebP."))?;
+    validate(dimensions, &seats, avatar_size)?;
+
+    let skin = GuildSkin {
+        guild_id: guild.get() as i64,
+        seats,
+        avatar_size: avatar_size as i32,
+        updated_at: Utc::now(),
+    };
+    let hat = jouch_assets(ctx).await?.scenes.default_hat();
+    let preview = preview_scene(ctx, &command.user, Some(guild), &skin.scene(hat), &image).await?;
+
+    command
+        .edit_response(
+            &ctx,
+            EditInteractionResponse::new()
+                .content("Here's how The Jouch will look. Save it?")
+                .new_attachment(preview)
+                .components(vec![CreateActionRow::Buttons(vec![
+                    CreateButton::new("skin_confirm")
+                        .style(ButtonStyle::Primary)
+                        .label("Save"),
+                    CreateButton::new("skin_cancel")
+                        .style(ButtonStyle::Secondary)
+                        .label("Cancel"),
+                ])]),
+        )
+        .await?;
+    let msg = command.get_response(&ctx).await?;
+
+    let Some(interaction) = msg
+        .await_component_interaction(ctx)
+        .author_id(command.user.id)
+        .timeout(CONFIRM_TIMEOUT)
+        .await
+    else {
+        command
+            .edit_response(
+                &ctx,
+                EditInteractionResponse::new()
+                    .content("Timed out; The Jouch wasn't changed.")
+                    .components(vec![]),
+            )
+            .await?;
+        return Ok(());
+    };
+
+    let confirmed = interaction.data.custom_id == "skin_confirm";
+    interaction
+        .create_response(
+            &ctx,
+            CreateInteractionResponse::UpdateMessage(
+                CreateInteractionResponseMessage::new()
+                    .content(if confirmed {
+                        "Saved; enjoy the new Jouch!"
+                    } else {
+                        "Canceled."
+                    })
+                    .components(vec![]),
+            ),
+        )
+        .await?;
+
+    if confirmed {
+        let data = ctx.data.read().await;
+        let db = data.get::<Db>().ok_or(anyhow!("Unable to get database"))?;
+        sqlx::query(
+            "INSERT INTO guild_skins (guild_id, image, seats, avatar_size, updated_at) VALUES ($1, $2, $3, $4, $5)
+                ON CONFLICT (guild_id) DO UPDATE SET image = EXCLUDED.image, seats = EXCLUDED.seats,
+                avatar_size = EXCLUDED.avatar_size, updated_at = EXCLUDED.updated_at",
+        )
+        .bind(skin.guild_id)
+        .bind(image)
+        .bind(sqlx::types::Json(&skin.seats))
+        .bind(skin.avatar_size)
+        .bind(skin.updated_at)
+        .execute(db.pool())
+        .await?;
+    }
+
+    Ok(())
+}
+
+pub async fn skin(ctx: &Context, command: &CommandInteraction) -> CommandResult {
+    let guild = command
+        .guild_id
+        .ok_or(anyhow!("Unable to get guild where command was sent"))?;
+
+    let options = command.data.options();
+    let subcommand = options
+        .first()
+        .ok_or(anyhow!("Please provide a valid subcommand"))?;
+
+    match (subcommand.name, &subcommand.value) {
+        ("set", ResolvedValue::SubCommand(args)) => {
+            let mut image = None;
+            let mut seats = None;
+            let mut avatar_size = DEFAULT_AVATAR_SIZE;
+            for arg in args {
+                match (arg.name, &arg.value) {
+                    ("image", ResolvedValue::Attachment(attachment)) => image = Some(*attachment),
+                    ("seats", ResolvedValue::String(value)) => seats = Some(*value),
+                    ("avatar_size", ResolvedValue::Integer(size)) => {
+                        avatar_size = u32::try_from(*size)
+                            .map_err(|_| anyhow!("Invalid avatar size passed!"))?
+                    }
+                    _ => bail!("Unknown/unimplemented option {}", arg.name),
+                }
+            }
+
+            set_skin(
+                ctx,
+                command,
+                guild,
+                image.ok_or(anyhow!("No image passed"))?,
+                seats.ok_or(anyhow!("No seats passed"))?,
+                avatar_size,
+            )
+            .await
+        }
+        ("clear", _) => {
+            {
+                let data = ctx.data.read().await;
+                let db = data.get::<Db>().ok_or(anyhow!("Unable to get database"))?;
+                sqlx::query("DELETE FROM guild_skins WHERE guild_id = $1")
+                    .bind(guild.get() as i64)
+                    .execute(db.pool())
+                    .await?;
+            }
+
+            command
+                .edit_response(
+                    &ctx,
+                    EditInteractionResponse::new().content("The Jouch is back to normal."),
+                )
+                .await?;
+
+            Ok(())
+        }
+        _ => Err(anyhow!("Unknown option {}", subcommand.name)),
+    }
+}
@@ -0,0 +1,96 @@
+use anyhow::{anyhow, bail};
+use chrono::{DateTime, NaiveDate, NaiveTime, Utc};
+use chrono_tz::Tz;
+use serenity::all::{
+    CommandInteraction, Context, EditInteractionResponse, GuildId, MessageBuilder, ResolvedValue,
+};
+use std::str::FromStr;
+
+use crate::db::{Db, GuildData};
+use crate::CommandResult;
+
+// Time zone of the guild, for working out where days start & end; UTC if it hasn't been set.
+pub fn guild_time_zone(guild_data: Option<&GuildData>) -> Tz {
+    guild_data
+        .and_then(|data| data.time_zone.as_deref())
+        .and_then(|name| Tz::from_str(name).ok())
+        .unwrap_or(Tz::UTC)
+}
+
+pub async fn guild_today(db: &Db, guild: GuildId) -> CommandResult<NaiveDate> {
+    let guild_data = db.read_guild(guild).await?;
+    Ok(Utc::now()
+        .with_timezone(&guild_time_zone(guild_data.as_ref()))
+        .date_naive())
+}
+
+// When the current day started in the guild's time zone.
+pub async fn guild_day_start(db: &Db, guild: GuildId) -> CommandResult<DateTime<Utc>> {
+    let guild_data = db.read_guild(guild).await?;
+    let now = Utc::now().with_timezone(&guild_time_zone(guild_data.as_ref()));
+    let midnight = now.date_naive().and_time(NaiveTime::MIN);
+    // a DST change can skip midnight entirely; close enough to go by UTC's midnight then.
+    Ok(midnight
+        .and_local_timezone(now.timezone())
+        .earliest()
+        .map(|start| start.with_timezone(&Utc))
+        .unwrap_or_else(|| midnight.and_utc()))
+}
+
+pub async fn timezone(ctx: &Context, command: &CommandInteraction) -> CommandResult {
+    let guild = command
+        .guild_id
+        .ok_or(anyhow!("Unable to get guild where command was sent"))?;
+    let options = command.data.options();
+    let subcommand = options
+        .first()
+        .ok_or(anyhow!("Please provide a valid subcommand"))?;
+
+    let data = ctx.data.read().await;
+    let db = data.get::<Db>().ok_or(anyhow!("Unable to get database"))?;
+
+    let time_zone = match (subcommand.name, &subcommand.value) {
+        ("set", ResolvedValue::SubCommand(args)) => {
+            let name = args
+                .iter()
+                .find_map(|arg| match (arg.name, &arg.value) {
+                    ("zone", ResolvedValue::String(name)) => Some(name.trim()),
+                    _ => None,
+                })
+                .ok_or(anyhow!("No time zone passed"))?;
+            let Ok(time_zone) = Tz::from_str(name) else {
+                bail!(
+                    "Unknown time zone {name}; use a name like America/Chicago or Europe/London."
+                );
+            };
+            db.update_guild(guild, "time_zone", time_zone.name())
+                .await?;
+            time_zone
+        }
+        ("clear", _) => {
+            db.update_guild(guild, "time_zone", None::<String>).await?;
+            Tz::UTC
+        }
+        _ => bail!("Unknown option {}", subcommand.name),
+    };
+
+    let mut builder = MessageBuilder::new();
+    builder
+        .push("This server's days now follow ")
+        .push_mono_safe(time_zone.name())
+        .push(format!(
+            "; it's {} there right now.",
+            Utc::now()
+                .with_timezone(&time_zone)
+                .format("%-I:%M %p on %A")
+        ));
+
+    command
+        .edit_response(
+            &ctx,
+            EditInteractionResponse::new().content(builder.build()),
+        )
+        .await?;
+
+    Ok(())
+}
@@ -0,0 +1,375 @@
+// Shared compositing for every image the bot draws.
+// Layers can be scaled, rotated by any angle, limited to an anti-aliased mask & given a drop shadow.
+use image::{imageops, GrayImage, Luma, Rgba, RgbaImage};
+
+// Shape to cut a layer down to; edges are anti-aliased using the distance to the edge of the shape.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Mask {
+    // the whole (rectangular) image
+    #[default]
+    None,
+    // the biggest circle that fits in the image, like Discord shows profile pictures
+    Circle,
+    // rectangle with corners rounded to the given radius (in source pixels)
+    RoundedRect(f32),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Shadow {
+    pub offset: (f32, f32),
+    // standard deviation of the gaussian blur, in pixels
+    pub blur: f32,
+    pub color: Rgba<u8>,
+}
+
+impl Default for Shadow {
+    fn default() -> Self {
+        Self {
+            offset: (6.0, 8.0),
+            blur: 6.0,
+            color: Rgba([0, 0, 0, 160]),
+        }
+    }
+}
+
+// Where & how to draw a layer.
+// x & y are the top left corner the layer would have if it wasn't scaled or rotated;
+// rotation (clockwise, in degrees) & scaling both happen around the center of the layer.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Placement {
+    pub x: f32,
+    pub y: f32,
+    pub scale: f32,
+    pub rotation: f32,
+    pub mask: Mask,
+    pub shadow: Option<Shadow>,
+}
+
+impl Placement {
+    pub fn at(x: f32, y: f32) -> Self {
+        Self {
+            x,
+            y,
+            scale: 1.0,
+            rotation: 0.0,
+            mask: Mask::None,
+            shadow: None,
+        }
+    }
+
+    pub fn scale(mut self, scale: f32) -> Self {
+        self.scale = scale;
+        self
+    }
+
+    pub fn rotate(mut self, degrees: f32) -> Self {
+        self.rotation = degrees;
+        self
+    }
+
+    pub fn mask(mut self, mask: Mask) -> Self {
+        self.mask = mask;
+        self
+    }
+
+    pub fn shadow(mut self, shadow: Shadow) -> Self {
+        self.shadow = Some(shadow);
+        self
+    }
+
+    fn is_identity(&self) -> bool {
+        self.scale == 1.0
+            && self.rotation.rem_euclid(360.0) == 0.0
+            && self.mask == Mask::None
+            && self.x.fract() == 0.0
+            && self.y.fract() == 0.0
+    }
+}
+
+// Draw source onto target using "source over" blending.
+pub fn draw(target: &mut RgbaImage, source: &RgbaImage, placement: &Placement) {
+    if placement.is_identity() && placement.shadow.is_none() {
+        // nothing to transform, so skip straight to blending.
+        composite(target, source, placement.x as i32, placement.y as i32);
+        return;
+    }
+
+    let (layer, x, y) = transform(source, placement);
+
+    if let Some(shadow) = placement.shadow {
+        draw_shadow(target, &layer, x, y, &shadow);
+    }
+
+    composite(target, &layer, x, y);
+}
+
+// Draw a thick, anti-aliased line with rounded ends.
+pub fn draw_line(
+    target: &mut RgbaImage,
+    from: (f32, f32),
+    to: (f32, f32),
+    radius: f32,
+    color: Rgba<u8>,
+) {
+    let (dx, dy) = (to.0 - from.0, to.1 - from.1);
+    let length_squared = (dx * dx + dy * dy).max(f32::EPSILON);
+
+    let min_x = (from.0.min(to.0) - radius - 1.0).max(0.0) as u32;
+    let min_y = (from.1.min(to.1) - radius - 1.0).max(0.0) as u32;
+    let max_x = ((from.0.max(to.0) + radius + 1.0).max(0.0) as u32).min(target.width());
+    let max_y = ((from.1.max(to.1) + radius + 1.0).max(0.0) as u32).min(target.height());
+
+    for y in min_y..max_y {
+        for x in min_x..max_x {
+            let (cx, cy) = (x as f32 + 0.5, y as f32 + 0.5);
+            // distance from the pixel to the closest point on the line
+            let t = (((cx - from.0) * dx + (cy - from.1) * dy) / length_squared).clamp(0.0, 1.0);
+            let distance = (from.0 + t * dx - cx).hypot(from.1 + t * dy - cy);
+            let coverage = (radius - distance + 0.5).clamp(0.0, 1.0);
+            if coverage > 0.0 {
+                let mut pixel = color;
+                pixel[3] = (pixel[3] as f32 * coverage).round() as u8;
+                blend_pixel(target.get_pixel_mut(x, y), pixel);
+            }
+        }
+    }
+}
+
+// Which segments of a seven segment display light up for each digit;
+// bits 0 to 5 go clockwise around the outside starting from the top, and bit 6 is the middle.
+const DIGIT_SEGMENTS: [u8; 10] = [
+    0b0111111, 0b0000110, 0b1011011, 0b1001111, 0b1100110, 0b1101101, 0b1111101, 0b0000111,
+    0b1111111, 0b1101111,
+];
+
+// Width of a number drawn by draw_digits at the given height.
+pub fn digits_width(digits: &str, height: f32) -> f32 {
+    let count = digits.chars().count() as f32;
+    (count * height * 0.75 - height * 0.25).max(0.0)
+}
+
+// Draw a number in seven segment style with its top left corner at (x, y); anything but digits leaves a gap.
+pub fn draw_digits(
+    target: &mut RgbaImage,
+    digits: &str,
+    (x, y): (f32, f32),
+    height: f32,
+    radius: f32,
+    color: Rgba<u8>,
+) {
+    let width = height / 2.0;
+    for (i, digit) in digits.chars().enumerate() {
+        let Some(segments) = digit
+            .to_digit(10)
+            .map(|digit| DIGIT_SEGMENTS[digit as usize])
+        else {
+            continue;
+        };
+
+        let mut left = x + i as f32 * height * 0.75;
+        // a lone segment on the right looks lopsided, so ones go down the middle instead.
+        if digit == '1' {
+            left -= width / 2.0;
+        }
+        let right = left + width;
+        let (top, middle, bottom) = (y, y + height / 2.0, y + height);
+        let lines = [
+            ((left, top), (right, top)),
+            ((right, top), (right, middle)),
+            ((right, middle), (right, bottom)),
+            ((left, bottom), (right, bottom)),
+            ((left, middle), (left, bottom)),
+            ((left, top), (left, middle)),
+            ((left, middle), (right, middle)),
+        ];
+        for (segment, (from, to)) in lines.iter().enumerate() {
+            if segments & (1 << segment) != 0 {
+                draw_line(target, *from, *to, radius, color);
+            }
+        }
+    }
+}
+
+// Signed distance (in source pixels) from a point to the edge of the mask; positive is inside.
+fn mask_distance(mask: Mask, width: f32, height: f32, x: f32, y: f32) -> f32 {
+    let (half_width, half_height) = (width / 2.0, height / 2.0);
+    let (x, y) = (x - half_width, y - half_height);
+
+    match mask {
+        Mask::None => rounded_rect_distance(x, y, half_width, half_height, 0.0),
+        Mask::Circle => half_width.min(half_height) - x.hypot(y),
+        Mask::RoundedRect(radius) => rounded_rect_distance(
+            x,
+            y,
+            half_width,
+            half_height,
+            radius.clamp(0.0, half_width.min(half_height)),
+        ),
+    }
+}
+
+fn rounded_rect_distance(x: f32, y: f32, half_width: f32, half_height: f32, radius: f32) -> f32 {
+    let qx = x.abs() - (half_width - radius);
+    let qy = y.abs() - (half_height - radius);
+    let outside = qx.max(0.0).hypot(qy.max(0.0));
+    let inside = qx.max(qy).min(0.0);
+    radius - outside - inside
+}
+
+// Bilinear sample of the source at the given (continuous) pixel coordinate, with premultiplied alpha.
+fn sample(source: &RgbaImage, x: f32, y: f32) -> [f32; 4] {
+    let max_x = source.width().saturating_sub(1) as f32;
+    let max_y = source.height().saturating_sub(1) as f32;
+    let (x, y) = (x.clamp(0.0, max_x), y.clamp(0.0, max_y));
+    let (x0, y0) = (x.floor(), y.floor());
+    let (fx, fy) = (x - x0, y - y0);
+
+    let mut out = [0.0; 4];
+    for (px, py, weight) in [
+        (x0, y0, (1.0 - fx) * (1.0 - fy)),
+        ((x0 + 1.0).min(max_x), y0, fx * (1.0 - fy)),
+        (x0, (y0 + 1.0).min(max_y), (1.0 - fx) * fy),
+        ((x0 + 1.0).min(max_x), (y0 + 1.0).min(max_y), fx * fy),
+    ] {
+        if weight <= 0.0 {
+            continue;
+        }
+        let pixel = source.get_pixel(px as u32, py as u32);
+        let alpha = pixel[3] as f32 / 255.0;
+        for channel in 0..3 {
+            out[channel] += pixel[channel] as f32 * alpha * weight;
+        }
+        out[3] += alpha * weight;
+    }
+    out
+}
+
+// sin & cos of an angle in degrees, exact for right angles so those don't pick up any blur.
+fn sin_cos(degrees: f32) -> (f32, f32) {
+    let degrees = degrees.rem_euclid(360.0);
+    if degrees == 0.0 {
+        (0.0, 1.0)
+    } else if degrees == 90.0 {
+        (1.0, 0.0)
+    } else if degrees == 180.0 {
+        (0.0, -1.0)
+    } else if degrees == 270.0 {
+        (-1.0, 0.0)
+    } else {
+        degrees.to_radians().sin_cos()
+    }
+}
+
+// Scale, rotate & mask the source into a new image covering just the area it ends up in.
+// Returns that image along with where its top left corner goes.
+fn transform(source: &RgbaImage, placement: &Placement) -> (RgbaImage, i32, i32) {
+    let (width, height) = (source.width() as f32, source.height() as f32);
+    let scale = placement.scale.max(f32::EPSILON);
+    let (center_x, center_y) = (
+        placement.x + width * scale / 2.0,
+        placement.y + height * scale / 2.0,
+    );
+    let (sin, cos) = sin_cos(placement.rotation);
+
+    let extent_x = (cos.abs() * width + sin.abs() * height) * scale / 2.0;
+    let extent_y = (sin.abs() * width + cos.abs() * height) * scale / 2.0;
+    let (x0, y0) = (
+        (center_x - extent_x).floor() as i32,
+        (center_y - extent_y).floor() as i32,
+    );
+    let (x1, y1) = (
+        (center_x + extent_x).ceil() as i32,
+        (center_y + extent_y).ceil() as i32,
+    );
+
+    let mut layer = RgbaImage::new((x1 - x0).max(0) as u32, (y1 - y0).max(0) as u32);
+
+    for (i, j, pixel) in layer.enumerate_pixels_mut() {
+        // work backwards from the center of the output pixel to where it is in the source.
+        let dx = (x0 + i as i32) as f32 + 0.5 - center_x;
+        let dy = (y0 + j as i32) as f32 + 0.5 - center_y;
+        let u = (dx * cos + dy * sin) / scale + width / 2.0;
+        let v = (dy * cos - dx * sin) / scale + height / 2.0;
+
+        let coverage =
+            (mask_distance(placement.mask, width, height, u, v) * scale + 0.5).clamp(0.0, 1.0);
+        if coverage <= 0.0 {
+            continue;
+        }
+
+        let [r, g, b, a] = sample(source, u - 0.5, v - 0.5);
+        if a <= 0.0 {
+            continue;
+        }
+        *pixel = Rgba([
+            (r / a).round() as u8,
+            (g / a).round() as u8,
+            (b / a).round() as u8,
+            (a * coverage * 255.0).round() as u8,
+        ]);
+    }
+
+    (layer, x0, y0)
+}
+
+fn draw_shadow(target: &mut RgbaImage, layer: &RgbaImage, x: i32, y: i32, shadow: &Shadow) {
+    // leave room for the blur to spread out past the edges of the layer.
+    let margin = (shadow.blur * 3.0).ceil().max(0.0) as u32;
+
+    let mut alpha = GrayImage::new(layer.width() + margin * 2, layer.height() + margin * 2);
+    for (i, j, pixel) in layer.enumerate_pixels() {
+        alpha.put_pixel(i + margin, j + margin, Luma([pixel[3]]));
+    }
+    if shadow.blur > 0.0 {
+        alpha = imageops::blur(&alpha, shadow.blur);
+    }
+
+    let shadow_image = RgbaImage::from_fn(alpha.width(), alpha.height(), |i, j| {
+        let mut pixel = shadow.color;
+        pixel[3] = (pixel[3] as u32 * alpha.get_pixel(i, j)[0] as u32 / 255) as u8;
+        pixel
+    });
+
+    composite(
+        target,
+        &shadow_image,
+        x - margin as i32 + shadow.offset.0.round() as i32,
+        y - margin as i32 + shadow.offset.1.round() as i32,
+    );
+}
+
+// Blend source onto target with its top left corner at (x, y), clipping anything outside of target.
+fn composite(target: &mut RgbaImage, source: &RgbaImage, x: i32, y: i32) {
+    let start_x = (-x).max(0) as u32;
+    let start_y = (-y).max(0) as u32;
+    let end_x = (target.width() as i64 - x as i64).clamp(0, source.width() as i64) as u32;
+    let end_y = (target.height() as i64 - y as i64).clamp(0, source.height() as i64) as u32;
+
+    for j in start_y..end_y {
+        for i in start_x..end_x {
+            blend_pixel(
+                target.get_pixel_mut((x + i as i32) as u32, (y + j as i32) as u32),
+                *source.get_pixel(i, j),
+            );
+        }
+    }
+}
+
+fn blend_pixel(target: &mut Rgba<u8>, source: Rgba<u8>) {
+    match source[3] {
+        0 => {}
+        255 => *target = source,
+        _ => {
+            let source_alpha = source[3] as f32 / 255.0;
+            let target_alpha = target[3] as f32 / 255.0 * (1.0 - source_alpha);
+            let out_alpha = source_alpha + target_alpha;
+            for channel in 0..3 {
+                target[channel] = ((source[channel] as f32 * source_alpha
+                    + target[channel] as f32 * target_alpha)
+                    / out_alpha)
+                    .round() as u8;
+            }
+            target[3] = (out_alpha * 255.0).round() as u8;
+        }
+    }
+}
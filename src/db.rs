@@ -1,11 +1,14 @@
 use crate::canned_responses::ResponseTable;
-use crate::commands::{birthday::BirthdayPrivacy, sit::JouchOrientation};
-use chrono::{DateTime, FixedOffset};
+use crate::commands::{
+    birthday::BirthdayPrivacy, consent::SitConsent, cooldown::CooldownTable, sit::JouchOrientation,
+};
+use chrono::{DateTime, FixedOffset, NaiveDate};
 use serde::{Deserialize, Serialize};
 use serenity::all::{GuildId, UserId};
 use serenity::prelude::TypeMapKey;
 use sqlx::{Encode, FromRow, PgPool, Postgres, QueryBuilder};
 use std::collections::HashSet;
+use the_jouch::flip_table::FlipTable;
 use tracing::debug;
 #[derive(Eq, PartialEq, Debug, Serialize, Deserialize, Clone, Default, FromRow)]
 pub struct UserKey {
@@ -89,6 +92,40 @@ impl Db {
         Ok(query.build_query_as().fetch_one(&self.db).await?)
     }
 
+    pub async fn update_user_settings<'q, T>(
+        &self,
+        user: UserId,
+        field: &str,
+        value: T,
+    ) -> anyhow::Result<UserSettings>
+    where
+        T: 'q + Encode<'q, Postgres> + sqlx::Type<Postgres>,
+    {
+        let mut query = QueryBuilder::new(&format!(
+            "INSERT INTO user_settings(user_id, {field}) VALUES ("
+        ));
+        query
+            .separated(",")
+            .push_bind(user.get() as i64)
+            .push_bind(value);
+        query.push(format!(
+            ") ON CONFLICT (user_id) DO UPDATE SET {field} = EXCLUDED.{field} RETURNING *"
+        ));
+
+        debug!("query: {}", query.sql());
+
+        Ok(query.build_query_as().fetch_one(&self.db).await?)
+    }
+
+    pub async fn read_user_settings(&self, user: UserId) -> anyhow::Result<Option<UserSettings>> {
+        Ok(
+            sqlx::query_as("SELECT * FROM user_settings WHERE user_id = $1")
+                .bind(user.get() as i64)
+                .fetch_optional(&self.db)
+                .await?,
+        )
+    }
+
     pub async fn read_guild(&self, guild: GuildId) -> anyhow::Result<Option<GuildData>> {
         Ok(sqlx::query_as("SELECT * FROM guilds WHERE id = $1")
             .bind(guild.get() as i64)
@@ -174,6 +211,12 @@ pub struct UserData {
     pub auto_nick: Option<String>,
     pub sit_count: i32,
     pub flip_count: i32,
+    pub accessory: Option<String>,
+    pub sit_streak: i32,
+    pub best_sit_streak: i32,
+    pub last_sit_day: Option<NaiveDate>,
+    pub games_won: i32,
+    pub seconds_seated: i64,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone, Default, FromRow)]
@@ -185,4 +228,21 @@ pub struct GuildData {
     pub canned_response_table: Option<ResponseTable>,
     #[serde(default)]
     pub jouch_orientation: JouchOrientation,
+    #[sqlx(json(nullable))]
+    pub flip_table: Option<FlipTable>,
+    #[sqlx(json(nullable))]
+    pub cooldowns: Option<CooldownTable>,
+    pub time_zone: Option<String>,
+    #[serde(default)]
+    pub global_rankings: bool,
+    #[serde(default)]
+    pub reaction_sits: bool,
+    pub reaction_emoji: Option<String>,
+}
+
+#[derive(Eq, PartialEq, Debug, Serialize, Deserialize, Clone, Default, FromRow)]
+pub struct UserSettings {
+    pub user_id: i64,
+    pub hide_from_global: bool,
+    pub sit_consent: SitConsent,
 }
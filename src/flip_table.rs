@@ -0,0 +1,69 @@
+// Odds of where The Jouch lands when it's flipped; each guild can tweak its own with /flipodds.
+use std::collections::BTreeMap;
+
+use rand::{seq::IndexedRandom, Rng};
+use serde::{Deserialize, Serialize};
+
+use crate::render::JouchOrientation;
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct FlipTable {
+    // relative chance of landing in each orientation; anything left out never comes up.
+    pub weights: BTreeMap<JouchOrientation, u32>,
+    // how much of its usual weight the orientation The Jouch is already in keeps, in percent.
+    // 0 means a flip always changes something.
+    pub repeat_percent: u32,
+}
+
+impl Default for FlipTable {
+    fn default() -> Self {
+        Self {
+            weights: [
+                (JouchOrientation::Normal, 100),
+                (JouchOrientation::UpsideDown, 100),
+                (JouchOrientation::RotatedLeft, 100),
+                (JouchOrientation::RotatedRight, 100),
+                (JouchOrientation::Tilted, 10),
+                (JouchOrientation::Levitating, 5),
+                (JouchOrientation::Orbit, 1),
+            ]
+            .iter()
+            .copied()
+            .collect(),
+            repeat_percent: 0,
+        }
+    }
+}
+
+impl FlipTable {
+    pub fn weight(&self, orientation: JouchOrientation) -> u32 {
+        self.weights.get(&orientation).copied().unwrap_or_default()
+    }
+
+    // The weight of everything that could come up when flipping from current, after the repeat penalty.
+    pub fn odds(&self, current: JouchOrientation) -> Vec<(JouchOrientation, u32)> {
+        self.weights
+            .iter()
+            .map(|(orientation, weight)| {
+                if *orientation == current {
+                    (*orientation, weight * self.repeat_percent / 100)
+                } else {
+                    (*orientation, *weight)
+                }
+            })
+            .filter(|(_, weight)| *weight > 0)
+            .collect()
+    }
+
+    // Where The Jouch lands when flipped from current; it stays put if nothing else has a chance.
+    pub fn pick<R: Rng + ?Sized>(
+        &self,
+        current: JouchOrientation,
+        rng: &mut R,
+    ) -> JouchOrientation {
+        self.odds(current)
+            .choose_weighted(rng, |(_, weight)| *weight)
+            .map(|(orientation, _)| *orientation)
+            .unwrap_or(current)
+    }
+}
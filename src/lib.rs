@@ -0,0 +1,5 @@
+pub mod accessories;
+pub mod compositing;
+pub mod flip_table;
+pub mod render;
+pub mod scenes;
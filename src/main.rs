@@ -1,3 +1,5 @@
+mod avatars;
+mod cache;
 mod canned_responses;
 mod commands;
 mod config;
@@ -9,30 +11,80 @@ use anyhow::anyhow;
 use serenity::all::{
     Command, CommandInteraction, CommandOptionType, CommandType, Context, CreateCommand,
     CreateCommandOption, CreateInteractionResponse, CreateInteractionResponseMessage,
-    EditInteractionResponse, EventHandler, GatewayIntents, GuildId, Interaction, Message, Ready,
+    EditInteractionResponse, EventHandler, GatewayIntents, GuildId, Interaction, Message, Reaction,
+    Ready,
 };
 use serenity::model::Permissions;
 use serenity::prelude::TypeMapKey;
 use serenity::{async_trait, Client};
 use tracing::{error, info, trace, warn};
 
-use commands::{autonick::*, birthday::*, clear::*, db_migration::migrate, novena::*, sit::*};
+use commands::{
+    accessory::*, achievements::*, autonick::*, birthday::*, clear::*, cooldown::*,
+    db_migration::migrate, flip_odds::*, global_rankings::*, jouch::*, novena::*,
+    reaction_sits::reaction_sits, seats::*, sit::*, skin::*, timezone::*,
+};
 
 pub type CommandResult<T = ()> = anyhow::Result<T>;
 
 struct Handler;
 
 impl Handler {
+    fn flip_style_option() -> CreateCommandOption {
+        CreateCommandOption::new(
+            CommandOptionType::String,
+            "style",
+            "how to show The Jouch (defaults to Emotes)",
+        )
+        .add_string_choice("Emotes", "Emotes")
+        .add_string_choice("Image", "Image")
+        .add_string_choice("Animated", "Animated")
+    }
+
+    fn orientation_option() -> CreateCommandOption {
+        let mut option = CreateCommandOption::new(
+            CommandOptionType::String,
+            "orientation",
+            "which way up The Jouch is",
+        );
+        for orientation in JouchOrientation::ALL {
+            option = option.add_string_choice(orientation.name(), format!("{orientation:?}"));
+        }
+        option
+    }
+
     fn create_commands() -> Vec<CreateCommand> {
         vec![
-            CreateCommand::new("sit").description("Sit on The Jouch")
-                .add_option(CreateCommandOption::new(CommandOptionType::User, "friend", "a friend to sit on The Jouch with")),
+            CreateCommand::new("sit").description("Sit on The Jouch").set_options({
+                let mut options = vec![
+                    CreateCommandOption::new(CommandOptionType::User, "friend", "a friend to sit on The Jouch with")
+                ];
+
+                // allow bringing a few more friends along.
+                for i in 2..=MAX_FRIENDS {
+                    options.push(CreateCommandOption::new(CommandOptionType::User, format!("friend{}", i), "another friend to sit on The Jouch with"));
+                }
+
+                options
+            }),
             CreateCommand::new("rankings").description("Check how often users have sat on and/or flipped The Jouch").set_options({
                 let mut options = vec![
                     CreateCommandOption::new(CommandOptionType::Integer, "sort", "what to sort users by")
                         // RankSortBy::Default is used to indicate no option was passed, and thus doesn't get added here.
                         .add_int_choice("Sits", RankSortBy::Sits as i32)
                         .add_int_choice("Flips", RankSortBy::Flips as i32)
+                        .add_int_choice("Daily streak", RankSortBy::Streak as i32)
+                        .add_int_choice("Time on The Jouch", RankSortBy::TimeSeated as i32),
+                    CreateCommandOption::new(CommandOptionType::Integer, "period", "how far back to count (defaults to all time)")
+                        .add_int_choice("Past day", RankPeriod::Day as i32)
+                        .add_int_choice("Past week", RankPeriod::Week as i32)
+                        .add_int_choice("Past month", RankPeriod::Month as i32)
+                        .add_int_choice("Past year", RankPeriod::Year as i32)
+                        .add_int_choice("All time", RankPeriod::All as i32),
+                    CreateCommandOption::new(CommandOptionType::Integer, "scope", "which servers to rank (defaults to this one)")
+                        .add_int_choice("This server", RankScope::Server as i32)
+                        .add_int_choice("Global", RankScope::Global as i32),
+                    CreateCommandOption::new(CommandOptionType::Boolean, "card", "also draw the top of the leaderboard as an image"),
                 ];
 
                 // allow up to 10 users to check in on.
@@ -66,9 +118,12 @@ impl Handler {
                     .add_sub_option(CreateCommandOption::new(CommandOptionType::String, "start", "date and/or time to start the novena (rounded to the hour, defaults to now)"))
                 )
                 .add_option(CreateCommandOption::new(CommandOptionType::SubCommand, "stop", "stop a novena")),
-            CreateCommand::new("flip").description("Flip The Jouch"),
+            CreateCommand::new("flip").description("Flip The Jouch")
+                .add_option(Handler::flip_style_option()),
             CreateCommand::new("flip").kind(CommandType::Message),
-            CreateCommand::new("rectify").description("Put The Jouch back upright"),
+            CreateCommand::new(SIT_WITH_COMMAND).kind(CommandType::User),
+            CreateCommand::new("rectify").description("Put The Jouch back upright")
+                .add_option(Handler::flip_style_option()),
             CreateCommand::new("birthday").description("Birthday tracking by The Jouch").add_integration_type(serenity::all::InstallationContext::Guild)
                 .add_option(CreateCommandOption::new(CommandOptionType::SubCommand, "set", "set your birthday")
                     .add_sub_option(CreateCommandOption::new(CommandOptionType::String, "birthday", "Birthday date string")
@@ -92,10 +147,105 @@ impl Handler {
                     )
                 )
                 .add_option(CreateCommandOption::new(CommandOptionType::SubCommand,"clear","clear automatic nickname")),
+            CreateCommand::new("accessory").description("Dress up for The Jouch").add_integration_type(serenity::all::InstallationContext::Guild)
+                .add_option(CreateCommandOption::new(CommandOptionType::SubCommand, "equip", "wear an accessory on The Jouch")
+                    .add_sub_option(CreateCommandOption::new(CommandOptionType::String, "accessory", "id of the accessory to wear (see /accessory list), leave out to take yours off"))
+                )
+                .add_option(CreateCommandOption::new(CommandOptionType::SubCommand, "list", "list accessories and how to unlock them")),
+            CreateCommand::new("skin").description("Use your own image of The Jouch in this server").add_integration_type(serenity::all::InstallationContext::Guild)
+                .default_member_permissions(Permissions::MANAGE_GUILD)
+                .add_option(CreateCommandOption::new(CommandOptionType::SubCommand, "set", "upload a new image of The Jouch, with a preview before it's saved")
+                    .add_sub_option(CreateCommandOption::new(CommandOptionType::Attachment, "image", "the new Jouch (PNG, GIF or WebP, 320x180 to 1920x1080)").required(true))
+                    .add_sub_option(CreateCommandOption::new(CommandOptionType::String, "seats", "top left corner of each avatar as x,y pairs separated by spaces, e.g. 240,64 580,64").required(true))
+                    .add_sub_option(CreateCommandOption::new(CommandOptionType::Integer, "avatar_size", "width & height of each avatar in pixels (defaults to 128)")
+                        .min_int_value(32)
+                        .max_int_value(512))
+                )
+                .add_option(CreateCommandOption::new(CommandOptionType::SubCommand, "clear", "go back to the default Jouch")),
+            CreateCommand::new("flipodds").description("Change where The Jouch lands when it's flipped in this server").add_integration_type(serenity::all::InstallationContext::Guild)
+                .default_member_permissions(Permissions::MANAGE_GUILD)
+                .add_option(CreateCommandOption::new(CommandOptionType::SubCommand, "show", "show the weight of each orientation and the odds of the next flip"))
+                .add_option(CreateCommandOption::new(CommandOptionType::SubCommand, "set", "set how likely an orientation is, relative to the others")
+                    .add_sub_option(Self::orientation_option().required(true))
+                    .add_sub_option(CreateCommandOption::new(CommandOptionType::Integer, "weight", "relative chance of landing this way (0 for never)")
+                        .min_int_value(0)
+                        .max_int_value(MAX_FLIP_WEIGHT as u64)
+                        .required(true))
+                )
+                .add_option(CreateCommandOption::new(CommandOptionType::SubCommand, "repeat", "set how likely The Jouch is to land the way it already is")
+                    .add_sub_option(CreateCommandOption::new(CommandOptionType::Integer, "percent", "percent of its usual weight the current orientation keeps (0 for never)")
+                        .min_int_value(0)
+                        .max_int_value(100)
+                        .required(true))
+                )
+                .add_option(CreateCommandOption::new(CommandOptionType::SubCommand, "reset", "go back to the default odds")),
+            CreateCommand::new("jouch").description("All about The Jouch itself").add_integration_type(serenity::all::InstallationContext::Guild)
+                .add_option(CreateCommandOption::new(CommandOptionType::SubCommand, "status", "which way up The Jouch is, who moved it last and how long it's been that way"))
+                .add_option(CreateCommandOption::new(CommandOptionType::SubCommand, "musicalchairs", "play musical chairs on The Jouch; one fewer seat each round, last one sitting wins")),
+            CreateCommand::new("cooldown").description("Change how long everyone has to wait between uses of a command in this server").add_integration_type(serenity::all::InstallationContext::Guild)
+                .default_member_permissions(Permissions::MANAGE_GUILD)
+                .add_option(CreateCommandOption::new(CommandOptionType::SubCommand, "show", "show the cooldown of each command"))
+                .add_option(CreateCommandOption::new(CommandOptionType::SubCommand, "set", "set the cooldown of a command")
+                    .add_sub_option({
+                        let mut option = CreateCommandOption::new(CommandOptionType::String, "command", "the command to change").required(true);
+                        for (command, _) in COOLDOWN_COMMANDS {
+                            option = option.add_string_choice(format!("/{command}"), command);
+                        }
+                        option
+                    })
+                    .add_sub_option(CreateCommandOption::new(CommandOptionType::Integer, "seconds", "how long each user has to wait between uses (0 for no cooldown)")
+                        .min_int_value(0)
+                        .max_int_value(MAX_COOLDOWN_SECONDS as u64)
+                        .required(true))
+                )
+                .add_option(CreateCommandOption::new(CommandOptionType::SubCommand, "reset", "go back to the default cooldowns")),
+            CreateCommand::new("timezone").description("Set the time zone this server's days follow, e.g. for sit streaks").add_integration_type(serenity::all::InstallationContext::Guild)
+                .default_member_permissions(Permissions::MANAGE_GUILD)
+                .add_option(CreateCommandOption::new(CommandOptionType::SubCommand, "set", "set the server's time zone")
+                    .add_sub_option(CreateCommandOption::new(CommandOptionType::String, "zone", "time zone name, e.g. America/Chicago or Europe/London").required(true))
+                )
+                .add_option(CreateCommandOption::new(CommandOptionType::SubCommand, "clear", "go back to UTC")),
+            CreateCommand::new("achievements").description("See which Jouch achievements you've unlocked in this server").add_integration_type(serenity::all::InstallationContext::Guild)
+                .add_option(CreateCommandOption::new(CommandOptionType::User, "user", "someone else to check on")),
+            CreateCommand::new("globalrankings").description("Choose whether this server is on the global leaderboard").add_integration_type(serenity::all::InstallationContext::Guild)
+                .default_member_permissions(Permissions::MANAGE_GUILD)
+                .add_option(CreateCommandOption::new(CommandOptionType::SubCommand, "join", "count this server's sits & flips on the global leaderboard"))
+                .add_option(CreateCommandOption::new(CommandOptionType::SubCommand, "leave", "take this server off the global leaderboard")),
+            CreateCommand::new("reactionsits").description("Sit on The Jouch with someone by reacting to their message").add_integration_type(serenity::all::InstallationContext::Guild)
+                .default_member_permissions(Permissions::MANAGE_GUILD)
+                .add_option(CreateCommandOption::new(CommandOptionType::SubCommand, "enable", "turn sitting by reaction on")
+                    .add_sub_option(CreateCommandOption::new(CommandOptionType::String, "emoji", "the emoji to react with (defaults to \u{1F6CB}\u{FE0F})")))
+                .add_option(CreateCommandOption::new(CommandOptionType::SubCommand, "disable", "turn sitting by reaction off")),
+            CreateCommand::new("stand").description("Get up off The Jouch").add_integration_type(serenity::all::InstallationContext::Guild),
+            CreateCommand::new("privacy").description("Check or change whether you show up on the global leaderboard & who can sit with you")
+                .add_option(CreateCommandOption::new(CommandOptionType::Boolean, "global_rankings", "whether to show up on the global leaderboard (leave out to check)"))
+                .add_option(CreateCommandOption::new(CommandOptionType::String, "sit_with", "whether friends can sit on The Jouch with you (leave out to check)")
+                    .add_string_choice("Ask me first", "Ask")
+                    .add_string_choice("Always allow", "Always")
+                    .add_string_choice("Never allow", "Never")),
         ]
     }
 
     async fn handle_app_command(ctx: &Context, command: CommandInteraction) -> CommandResult {
+        match check_cooldown(ctx, &command).await {
+            Ok(Some(remaining)) => {
+                command
+                    .create_response(
+                        &ctx.http,
+                        CreateInteractionResponse::Message(
+                            CreateInteractionResponseMessage::new()
+                                .content(cooldown_message(&command, remaining))
+                                .ephemeral(true),
+                        ),
+                    )
+                    .await?;
+                return Ok(());
+            }
+            Ok(None) => {}
+            // Not worth blocking the command over.
+            Err(err) => warn!("Unable to check cooldown for {}: {err}", command.data.name),
+        }
+
         command
             .create_response(
                 &ctx.http,
@@ -104,7 +254,7 @@ impl Handler {
             .await?;
 
         let content = match command.data.name.as_str() {
-            "sit" => sit(&ctx, &command).await,
+            "sit" | SIT_WITH_COMMAND => sit(&ctx, &command).await,
             "rankings" => rank(&ctx, &command).await,
             "flip" => flip(ctx, &command).await,
             "rectify" => rectify(ctx, &command).await,
@@ -113,10 +263,23 @@ impl Handler {
             "migrate" => migrate(&ctx, &command).await,
             "autonick" => autonick(&ctx, &command).await,
             "novena" => novena(&ctx, &command).await,
+            "accessory" => accessory(ctx, &command).await,
+            "skin" => skin(ctx, &command).await,
+            "flipodds" => flip_odds(ctx, &command).await,
+            "jouch" => jouch(ctx, &command).await,
+            "cooldown" => cooldown(ctx, &command).await,
+            "timezone" => timezone(ctx, &command).await,
+            "achievements" => achievements(ctx, &command).await,
+            "globalrankings" => global_rankings(ctx, &command).await,
+            "privacy" => privacy(ctx, &command).await,
+            "stand" => stand(ctx, &command).await,
+            "reactionsits" => reaction_sits(ctx, &command).await,
             _ => Err(anyhow!("not implemented :(")),
         };
 
         if let Err(content) = content {
+            // nothing happened, so it shouldn't cost the user their cooldown.
+            refund_cooldown(ctx, &command).await;
             command
                 .edit_response(
                     &ctx.http,
@@ -198,10 +361,11 @@ impl EventHandler for Handler {
     }
 
     async fn cache_ready(&self, ctx: Context, _guilds: Vec<GuildId>) {
-        // Spawn the nickname, novena, & birthday checkers.
+        // Spawn the nickname, novena, birthday, & seat checkers.
         tokio::spawn(check_nicks_loop(ctx.clone()));
         tokio::spawn(check_birthdays_loop(ctx.clone()));
         tokio::spawn(check_novenas_loop(ctx.clone()));
+        tokio::spawn(check_seats_loop(ctx.clone()));
     }
     async fn message(&self, ctx: Context, msg: Message) {
         // Ignore messages from bots to avoid risking an infinite response loop.
@@ -212,11 +376,15 @@ impl EventHandler for Handler {
             }
         }
     }
+    async fn reaction_add(&self, ctx: Context, reaction: Reaction) {
+        if let Err(err) = commands::reaction_sits::process(&ctx, &reaction).await {
+            warn!("Error processing reaction sit: {:?}", err);
+        }
+    }
 }
 
 struct EnvItemsContainer {
     test_guild: Option<u64>,
-    assets_dir: PathBuf,
 }
 
 impl TypeMapKey for EnvItemsContainer {
@@ -238,6 +406,19 @@ async fn main() {
     let token = std::env::var("DISCORD_TOKEN").expect("DISCORD_TOKEN must be set");
     let app_id = std::env::var("DISCORD_APP_ID").expect("DISCORD_APP_ID must be set");
     let test_guild = std::env::var("DISCORD_TEST_GUILD").ok();
+    let avatar_cache_dir = std::env::var("AVATAR_CACHE_DIR").ok();
+
+    // Fail right away if any assets are missing, rather than the first time someone sits.
+    let assets = the_jouch::render::JouchAssets::load(&PathBuf::from("assets"))
+        .expect("Unable to load assets!");
+    let achievements = commands::achievements::AchievementCatalog::load(&PathBuf::from("assets"))
+        .unwrap_or_else(|err| {
+            panic!(
+                "Unable to load {}: {}",
+                commands::achievements::ACHIEVEMENTS_FILE,
+                err
+            )
+        });
 
     let db = sqlx::PgPool::connect(&database_url).await.unwrap();
 
@@ -269,16 +450,23 @@ async fn main() {
 
     trace!("loaded config data: {:#?}", config);
 
-    let shuttle_items = EnvItemsContainer {
-        test_guild,
-        assets_dir: PathBuf::from("assets"),
-    };
+    let shuttle_items = EnvItemsContainer { test_guild };
+
+    let avatar_cache = avatars::AvatarCache::new(avatar_cache_dir.map(PathBuf::from))
+        .expect("Unable to create avatar cache!");
 
     {
         let mut data = client.data.write().await;
         data.insert::<db::Db>(db::Db::new(db));
         data.insert::<config::Config>(config);
         data.insert::<EnvItemsContainer>(shuttle_items);
+        data.insert::<avatars::AvatarCache>(std::sync::Arc::new(avatar_cache));
+        data.insert::<commands::sit::JouchAssetsKey>(std::sync::Arc::new(assets));
+        data.insert::<commands::achievements::AchievementsKey>(std::sync::Arc::new(achievements));
+        data.insert::<commands::sit::RenderCache>(std::sync::Arc::new(
+            commands::sit::RenderCache::new(),
+        ));
+        data.insert::<commands::cooldown::Cooldowns>(Default::default());
     }
 
     // start listening for events by starting the number of shards Discord thinks we need
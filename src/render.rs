@@ -0,0 +1,894 @@
+// Everything to do with drawing The Jouch, kept apart from Discord & the database
+// so it can also be driven from local files (see src/bin/jouch-render.rs).
+use std::collections::HashMap;
+use std::io::Cursor;
+use std::path::Path;
+
+use anyhow::anyhow;
+use enum_utils::FromStr;
+use image::codecs::{gif::GifDecoder, gif::GifEncoder, png::PngDecoder, webp::WebPDecoder};
+use image::{
+    imageops, imageops::FilterType, AnimationDecoder, Delay, DynamicImage, Rgba, RgbaImage,
+};
+use image::{Frame, ImageFormat};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+
+use crate::accessories::{AccessoryCatalog, Slot, CATALOG_FILE, REFERENCE_AVATAR_SIZE};
+use crate::compositing::{self, Mask, Placement, Shadow};
+use crate::scenes::{Scene, SceneManifest, MANIFEST_FILE};
+
+// size of the flipper's avatar in /flip & /rectify images; sits use the size from their scene.
+pub const FLIPPER_AVATAR_SIZE: u32 = 128;
+
+// Frames shorter than this get bumped up to 100ms, which is what browsers do with GIFs anyway.
+const MIN_FRAME_DELAY: u32 = 20;
+// Cap animated output so a long avatar loop doesn't turn into an enormous file.
+const MAX_ANIMATION_MS: u32 = 10_000;
+// Roughly 60 frames of a single panel; wider scenes get proportionally fewer frames.
+const MAX_ANIMATION_PIXELS: u64 = 960 * 540 * 60;
+// Stop decoding an animation after this many pixels' worth of frames, so a huge one can't eat all the memory.
+const MAX_DECODED_PIXELS: u64 = MAX_ANIMATION_PIXELS;
+
+// Layout of the image posted by /flip & /rectify; the couch goes in a square on the left & the flipper on the right.
+pub const FLIP_SCENE_SIZE: (u32, u32) = (1280, 960);
+const FLIPPER_POSITION: (u32, u32) = (1066, 480);
+// top left & size of the flipper's torso, and (shoulder, hand) for each of their arms, which are thrown up in the air.
+const FLIPPER_TORSO: ((f32, f32), (u32, u32)) = ((1082.0, 610.0), (96, 300));
+const FLIPPER_ARMS: [((f32, f32), (f32, f32)); 2] = [
+    ((1095.0, 640.0), (1020.0, 500.0)),
+    ((1165.0, 640.0), (1240.0, 500.0)),
+];
+const FLIPPER_SHIRT: Rgba<u8> = Rgba([96, 130, 150, 255]);
+const FLIPPER_SKIN: Rgba<u8> = Rgba([233, 196, 170, 255]);
+// how each quarter turn of a tumble is split up, and how long to sit on the final orientation before looping.
+const TUMBLE_FRAMES_PER_TURN: u32 = 4;
+const TUMBLE_STEP_MS: u32 = 50;
+const TUMBLE_HOLD_MS: u32 = 3000;
+const TUMBLE_HOP: f32 = 60.0;
+// how high The Jouch floats when levitating, as a fraction of its height.
+const LEVITATE_HEIGHT: f32 = 0.15;
+const LEVITATE_GLOW: Rgba<u8> = Rgba([190, 150, 255, 150]);
+// In orbit, The Jouch is a little speck drifting across the stars, above where it used to be.
+const ORBIT_SCALE: f32 = 0.3;
+const ORBIT_ROTATION: f32 = 30.0;
+const ORBIT_LIFT: f32 = 0.3;
+const PIXELS_PER_STAR: u64 = 2000;
+const STARFIELD_SEED: u64 = 0x10c4;
+
+// Layout of the /rankings card: The Jouch up top, then a row for each of the top sitters.
+pub const CARD_AVATAR_SIZE: u32 = 112;
+pub const CARD_ROWS: usize = 5;
+const CARD_WIDTH: u32 = 960;
+const CARD_HEADER_HEIGHT: u32 = 320;
+const CARD_ROW_HEIGHT: u32 = 144;
+const CARD_PADDING: f32 = 40.0;
+const CARD_MEDAL_SIZE: u32 = 88;
+const CARD_DIGIT_HEIGHT: f32 = 64.0;
+const CARD_ICON_HEIGHT: f32 = 72.0;
+const CARD_BACKGROUND: Rgba<u8> = Rgba([43, 45, 49, 255]);
+const CARD_STRIPE: Rgba<u8> = Rgba([49, 51, 56, 255]);
+const CARD_HEADER: Rgba<u8> = Rgba([30, 31, 34, 255]);
+const CARD_TEXT: Rgba<u8> = Rgba([242, 243, 245, 255]);
+// gold, silver & bronze, then a plain badge for everyone else; each with a darker rim.
+const MEDAL_COLORS: [(Rgba<u8>, Rgba<u8>); 4] = [
+    (Rgba([255, 204, 77, 255]), Rgba([186, 135, 20, 255])),
+    (Rgba([208, 214, 222, 255]), Rgba([128, 136, 148, 255])),
+    (Rgba([222, 140, 82, 255]), Rgba([140, 78, 36, 255])),
+    (Rgba([88, 101, 242, 255]), Rgba([52, 61, 160, 255])),
+];
+
+#[derive(
+    Default,
+    Clone,
+    Copy,
+    Debug,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Serialize,
+    Deserialize,
+    FromStr,
+    sqlx::Type,
+)]
+#[sqlx(type_name = "jouch_orientation")]
+#[enumeration(case_insensitive)]
+pub enum JouchOrientation {
+    #[default]
+    Normal,
+    UpsideDown,
+    RotatedLeft,
+    RotatedRight,
+    // the rare ones; see FlipTable for how rare.
+    Tilted,
+    Levitating,
+    Orbit,
+}
+
+impl JouchOrientation {
+    pub const ALL: [JouchOrientation; 7] = [
+        JouchOrientation::Normal,
+        JouchOrientation::UpsideDown,
+        JouchOrientation::RotatedLeft,
+        JouchOrientation::RotatedRight,
+        JouchOrientation::Tilted,
+        JouchOrientation::Levitating,
+        JouchOrientation::Orbit,
+    ];
+
+    pub fn to_emotes(&self) -> &str {
+        match self {
+            JouchOrientation::Normal => {
+                "<:jouchup1:1117080763565879397><:jouchup2:1117080764572520449>"
+            }
+            JouchOrientation::UpsideDown => {
+                "<:jouchdn1:1117080756309721139><:jouchdn2:1117080758612410401> "
+            }
+            JouchOrientation::RotatedLeft => {
+                "<:jouchl1:1117080760185270366>\n<:jouchl2:1117080761615519814>"
+            }
+            JouchOrientation::RotatedRight => {
+                "<:jouchr1:1117079201321861150>\n<:jouchr2:1117079202890530906>"
+            }
+            JouchOrientation::Tilted => {
+                "<:jouchr1:1117079201321861150>\n\u{2003}<:jouchr2:1117079202890530906>"
+            }
+            JouchOrientation::Levitating => {
+                "\u{2728}<:jouchup1:1117080763565879397><:jouchup2:1117080764572520449>\u{2728}"
+            }
+            JouchOrientation::Orbit => {
+                "\u{1F30D} \u{22EF} <:jouchup1:1117080763565879397><:jouchup2:1117080764572520449>\u{1F680}"
+            }
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        match self {
+            JouchOrientation::Normal => "upright",
+            JouchOrientation::UpsideDown => "upside down",
+            JouchOrientation::RotatedLeft => "on its left side",
+            JouchOrientation::RotatedRight => "on its right side",
+            JouchOrientation::Tilted => "tilted 45\u{b0}",
+            JouchOrientation::Levitating => "levitating",
+            JouchOrientation::Orbit => "in orbit",
+        }
+    }
+}
+
+// Where The Jouch ends up in the /flip & /rectify image for each orientation.
+struct Pose {
+    // clockwise, in degrees
+    rotation: f32,
+    // how far above its usual spot it is, as a fraction of the size of the couch
+    lift: f32,
+    scale: f32,
+}
+
+impl JouchOrientation {
+    fn pose(&self) -> Pose {
+        let (rotation, lift, scale) = match self {
+            JouchOrientation::Normal => (0.0, 0.0, 1.0),
+            JouchOrientation::RotatedRight => (90.0, 0.0, 1.0),
+            JouchOrientation::UpsideDown => (180.0, 0.0, 1.0),
+            JouchOrientation::RotatedLeft => (270.0, 0.0, 1.0),
+            JouchOrientation::Tilted => (45.0, 0.0, 1.0),
+            JouchOrientation::Levitating => (0.0, LEVITATE_HEIGHT, 1.0),
+            JouchOrientation::Orbit => (ORBIT_ROTATION, ORBIT_LIFT, ORBIT_SCALE),
+        };
+        Pose {
+            rotation,
+            lift,
+            scale,
+        }
+    }
+
+    // Put a finished image of The Jouch (and whoever is on it) into this orientation.
+    pub fn transform(&self, image: DynamicImage) -> DynamicImage {
+        match self {
+            JouchOrientation::Normal => image,
+            JouchOrientation::UpsideDown => image.rotate180(),
+            JouchOrientation::RotatedLeft => image.rotate270(),
+            JouchOrientation::RotatedRight => image.rotate90(),
+            JouchOrientation::Tilted => {
+                let image = image.into_rgba8();
+                let (width, height) = (image.width() as f32, image.height() as f32);
+                // big enough for the corners once it's turned
+                let side = ((width + height) * std::f32::consts::FRAC_1_SQRT_2).ceil();
+                let mut tilted = RgbaImage::new(side as u32, side as u32);
+                compositing::draw(
+                    &mut tilted,
+                    &image,
+                    &Placement::at((side - width) / 2.0, (side - height) / 2.0).rotate(45.0),
+                );
+                DynamicImage::ImageRgba8(tilted)
+            }
+            JouchOrientation::Levitating => {
+                let image = image.into_rgba8();
+                let gap = (image.height() as f32 * LEVITATE_HEIGHT).round();
+                let mut levitating = RgbaImage::new(image.width(), image.height() + gap as u32);
+                // held up by a soft, magical glow where it used to sit
+                let mut glow = RgbaImage::new(image.width(), gap as u32);
+                let (glow_width, glow_height) = (image.width() * 3 / 4, glow.height() / 3);
+                compositing::draw(
+                    &mut glow,
+                    &RgbaImage::from_pixel(glow_width, glow_height, LEVITATE_GLOW),
+                    &Placement::at(
+                        (image.width() - glow_width) as f32 / 2.0,
+                        glow_height as f32,
+                    )
+                    .mask(Mask::RoundedRect(glow_height as f32 / 2.0)),
+                );
+                compositing::draw(
+                    &mut levitating,
+                    &imageops::blur(&glow, gap / 8.0),
+                    &Placement::at(0.0, image.height() as f32),
+                );
+                compositing::draw(&mut levitating, &image, &Placement::at(0.0, 0.0));
+                DynamicImage::ImageRgba8(levitating)
+            }
+            JouchOrientation::Orbit => {
+                let image = image.into_rgba8();
+                let (width, height) = (image.width(), image.height());
+                let mut orbit = starfield(width, height);
+                // off to the right & up a bit
+                let center = (width as f32 * 0.7, height as f32 * (0.5 - ORBIT_LIFT / 2.0));
+                compositing::draw(
+                    &mut orbit,
+                    &image,
+                    &Placement::at(
+                        center.0 - width as f32 * ORBIT_SCALE / 2.0,
+                        center.1 - height as f32 * ORBIT_SCALE / 2.0,
+                    )
+                    .scale(ORBIT_SCALE)
+                    .rotate(ORBIT_ROTATION),
+                );
+                DynamicImage::ImageRgba8(orbit)
+            }
+        }
+    }
+}
+
+// Black sky with a scattering of stars; always the same stars for a given size.
+fn starfield(width: u32, height: u32) -> RgbaImage {
+    let mut sky = RgbaImage::from_pixel(width, height, Rgba([0, 0, 0, 255]));
+    let mut rng = StdRng::seed_from_u64(STARFIELD_SEED);
+    for _ in 0..(width as u64 * height as u64 / PIXELS_PER_STAR) {
+        let point = (
+            rng.random_range(0.0..width as f32),
+            rng.random_range(0.0..height as f32),
+        );
+        let brightness = rng.random_range(120..=255);
+        compositing::draw_line(
+            &mut sky,
+            point,
+            point,
+            rng.random_range(0.5..1.5),
+            Rgba([brightness, brightness, brightness, 255]),
+        );
+    }
+    sky
+}
+
+// A (possibly) animated image; a still image is just a single frame.
+#[derive(Clone)]
+pub struct Animation {
+    // each frame along with how many milliseconds it's shown for.
+    frames: Vec<(RgbaImage, u32)>,
+}
+
+impl Animation {
+    pub fn still(image: DynamicImage) -> Self {
+        Self {
+            frames: vec![(image.into_rgba8(), 0)],
+        }
+    }
+
+    pub fn is_animated(&self) -> bool {
+        self.frames.len() > 1
+    }
+
+    pub fn width(&self) -> u32 {
+        self.frames[0].0.width()
+    }
+
+    pub fn height(&self) -> u32 {
+        self.frames[0].0.height()
+    }
+
+    pub fn duration(&self) -> u32 {
+        self.frames.iter().map(|(_, delay)| delay).sum()
+    }
+
+    // get the frame that is showing at the given time, looping as needed.
+    pub fn frame_at(&self, time: u32) -> &RgbaImage {
+        if self.is_animated() {
+            let mut time = time % self.duration().max(1);
+            for (frame, delay) in &self.frames {
+                if time < *delay {
+                    return frame;
+                }
+                time -= delay;
+            }
+        }
+        &self.frames[0].0
+    }
+
+    pub fn map(self, f: impl Fn(RgbaImage) -> RgbaImage) -> Self {
+        Self {
+            frames: self
+                .frames
+                .into_iter()
+                .map(|(frame, delay)| (f(frame), delay))
+                .collect(),
+        }
+    }
+
+    // resize every frame to fit in a size x size square, e.g. to fit an avatar in a seat.
+    pub fn resize(self, size: u32) -> Self {
+        self.map(|frame| {
+            DynamicImage::ImageRgba8(frame)
+                .resize(size, size, FilterType::CatmullRom)
+                .into_rgba8()
+        })
+    }
+}
+
+impl From<Vec<Frame>> for Animation {
+    fn from(frames: Vec<Frame>) -> Self {
+        Self {
+            frames: frames
+                .into_iter()
+                .map(|frame| {
+                    let (numer, denom) = frame.delay().numer_denom_ms();
+                    let delay = numer / denom.max(1);
+                    (
+                        frame.into_buffer(),
+                        if delay < MIN_FRAME_DELAY { 100 } else { delay },
+                    )
+                })
+                .collect(),
+        }
+    }
+}
+
+// Take frames until there's enough for the longest animation that gets drawn, or until they'd take up too much memory.
+fn collect_frames(frames: image::Frames) -> anyhow::Result<Vec<Frame>> {
+    let mut collected = Vec::new();
+    let (mut pixels, mut duration) = (0, 0);
+    for frame in frames {
+        let frame = frame?;
+        let (numer, denom) = frame.delay().numer_denom_ms();
+        pixels += frame.buffer().width() as u64 * frame.buffer().height() as u64;
+        duration += numer / denom.max(1);
+        collected.push(frame);
+        if pixels >= MAX_DECODED_PIXELS || duration >= MAX_ANIMATION_MS {
+            break;
+        }
+    }
+    Ok(collected)
+}
+
+// Decode the frames of an animated GIF, WebP or PNG (as many as will get drawn), or just the one for anything else.
+pub fn decode_animation(buffer: &[u8]) -> anyhow::Result<Animation> {
+    let frames = match image::guess_format(buffer) {
+        Ok(ImageFormat::Gif) => {
+            collect_frames(GifDecoder::new(Cursor::new(buffer))?.into_frames())?
+        }
+        Ok(ImageFormat::Png) => {
+            let decoder = PngDecoder::new(Cursor::new(buffer))?;
+            if decoder.is_apng()? {
+                collect_frames(decoder.apng()?.into_frames())?
+            } else {
+                vec![]
+            }
+        }
+        Ok(ImageFormat::WebP) => {
+            let decoder = WebPDecoder::new(Cursor::new(buffer))?;
+            if decoder.has_animation() {
+                collect_frames(decoder.into_frames())?
+            } else {
+                vec![]
+            }
+        }
+        _ => vec![],
+    };
+
+    Ok(if frames.is_empty() {
+        Animation::still(if let Some(img) = webp::Decoder::new(buffer).decode() {
+            img.to_image()
+        } else {
+            image::load_from_memory(buffer)?
+        })
+    } else {
+        frames.into()
+    })
+}
+
+pub fn load_animation(path: &Path) -> anyhow::Result<Animation> {
+    decode_animation(&std::fs::read(path)?)
+}
+
+// Every image the bot draws with, decoded once at startup so a missing or broken asset is caught right away.
+pub struct JouchAssets {
+    pub scenes: SceneManifest,
+    pub accessories: AccessoryCatalog,
+    images: HashMap<String, Animation>,
+}
+
+impl JouchAssets {
+    pub fn load(dir: &Path) -> anyhow::Result<Self> {
+        let scenes = SceneManifest::load(dir)
+            .map_err(|err| anyhow!("Unable to load {}: {}", MANIFEST_FILE, err))?;
+        let accessories = AccessoryCatalog::load(dir)
+            .map_err(|err| anyhow!("Unable to load {}: {}", CATALOG_FILE, err))?;
+
+        let mut images = HashMap::new();
+        for name in scenes.images().chain(accessories.images()) {
+            if images.contains_key(name) {
+                continue;
+            }
+            let path = dir.join(name);
+            let image = load_animation(&path)
+                .map_err(|err| anyhow!("Unable to load asset {:?}: {}", path, err))?;
+            images.insert(name.to_owned(), image);
+        }
+
+        Ok(Self {
+            scenes,
+            accessories,
+            images,
+        })
+    }
+
+    pub fn get(&self, name: &str) -> anyhow::Result<&Animation> {
+        self.images
+            .get(name)
+            .ok_or(anyhow!("Asset {name} wasn't loaded"))
+    }
+}
+
+// Something to be drawn onto a scene; layers are drawn in order, so later ones end up on top.
+pub struct Layer {
+    pub image: Animation,
+    pub placement: Placement,
+}
+
+// Work out the start time & length of each output frame, which is every time any of the layers changes frames.
+// If that ends up being too many frames, fall back to evenly spaced ones instead.
+fn timeline(layers: &[Layer], max_frames: usize) -> Vec<(u32, u32)> {
+    let animated: Vec<&Animation> = layers
+        .iter()
+        .map(|layer| &layer.image)
+        .filter(|image| image.is_animated())
+        .collect();
+
+    let total = animated
+        .iter()
+        .map(|image| image.duration())
+        .max()
+        .unwrap_or_default()
+        .min(MAX_ANIMATION_MS);
+
+    if total == 0 || max_frames <= 1 {
+        return vec![(0, 0)];
+    }
+
+    let mut starts = Vec::new();
+    for image in animated {
+        let mut time = 0;
+        for (_, delay) in image.frames.iter().cycle() {
+            if time >= total {
+                break;
+            }
+            starts.push(time);
+            time += delay;
+        }
+    }
+    starts.sort_unstable();
+    starts.dedup();
+
+    if starts.len() > max_frames {
+        let step = (total / max_frames as u32).max(MIN_FRAME_DELAY);
+        starts = (0..total).step_by(step as usize).collect();
+    }
+
+    starts
+        .iter()
+        .enumerate()
+        .map(|(i, start)| (*start, starts.get(i + 1).unwrap_or(&total) - start))
+        .collect()
+}
+
+// Draw all the layers, rotate to the orientation of The Jouch and encode the result.
+// Gives a PNG when nothing is animated, otherwise a looping GIF with every frame drawn the same way.
+pub fn render_scene(
+    layers: &[Layer],
+    width: u32,
+    height: u32,
+    orientation: JouchOrientation,
+) -> anyhow::Result<(Vec<u8>, ImageFormat)> {
+    let max_frames = (MAX_ANIMATION_PIXELS / (width as u64 * height as u64).max(1)) as usize;
+    let timeline = timeline(layers, max_frames);
+
+    let render_frame = |time: u32| -> DynamicImage {
+        let mut frame = RgbaImage::new(width, height);
+        for layer in layers {
+            compositing::draw(&mut frame, layer.image.frame_at(time), &layer.placement);
+        }
+        orientation.transform(DynamicImage::ImageRgba8(frame))
+    };
+
+    let mut image_bytes: Vec<u8> = vec![];
+
+    if let [(time, _)] = timeline.as_slice() {
+        render_frame(*time).write_to(&mut Cursor::new(&mut image_bytes), ImageFormat::Png)?;
+        return Ok((image_bytes, ImageFormat::Png));
+    }
+
+    {
+        let mut encoder = GifEncoder::new_with_speed(&mut image_bytes, 10);
+        encoder.set_repeat(image::codecs::gif::Repeat::Infinite)?;
+        for (time, delay) in timeline {
+            encoder.encode_frame(Frame::from_parts(
+                render_frame(time).into_rgba8(),
+                0,
+                0,
+                Delay::from_numer_denom_ms(delay, 1),
+            ))?;
+        }
+    }
+
+    Ok((image_bytes, ImageFormat::Gif))
+}
+
+// The layers for the /flip & /rectify image: the empty couch in its new orientation, with the flipper off to the side.
+// When tumbling, the couch spins from the old orientation to the new one, with a little hop on the way.
+pub fn flip_layers(
+    couch: &RgbaImage,
+    flipper: Animation,
+    from: JouchOrientation,
+    to: JouchOrientation,
+    tumble: bool,
+) -> Vec<Layer> {
+    let size = couch.width().max(couch.height());
+    // shrink the couch a bit so it still fits in its square while it's partway through turning.
+    let scale = size as f32 / (couch.width() as f32).hypot(couch.height() as f32);
+
+    let target = to.pose();
+    let (start, turning) = if tumble {
+        // always go at least one full spin, even if it lands the same way up.
+        let start = from.pose();
+        let turning = (target.rotation - start.rotation).rem_euclid(360.0);
+        (start, if turning == 0.0 { 360.0 } else { turning })
+    } else {
+        (to.pose(), 0.0)
+    };
+    let steps = (turning / 90.0 * TUMBLE_FRAMES_PER_TURN as f32).ceil() as u32;
+
+    let mut frames = Vec::new();
+    for step in 0..=steps {
+        let progress = if steps == 0 {
+            1.0
+        } else {
+            step as f32 / steps as f32
+        };
+        let hop = TUMBLE_HOP * (progress * std::f32::consts::PI).sin();
+        let lift = size as f32 * (start.lift + (target.lift - start.lift) * progress);
+        let couch_scale = scale * (start.scale + (target.scale - start.scale) * progress);
+
+        let mut frame = RgbaImage::new(size, size);
+        compositing::draw(
+            &mut frame,
+            couch,
+            // centered in the square, before being lifted
+            &Placement::at(
+                (size as f32 - couch.width() as f32 * couch_scale) / 2.0,
+                (size as f32 - couch.height() as f32 * couch_scale) / 2.0 - hop - lift,
+            )
+            .scale(couch_scale)
+            .rotate(start.rotation + turning * progress),
+        );
+        frames.push((
+            frame,
+            if step == steps {
+                TUMBLE_HOLD_MS
+            } else {
+                TUMBLE_STEP_MS
+            },
+        ));
+    }
+
+    let mut body = RgbaImage::new(FLIP_SCENE_SIZE.0, FLIP_SCENE_SIZE.1);
+    let ((torso_x, torso_y), (torso_width, torso_height)) = FLIPPER_TORSO;
+    // outlines are drawn a bit bigger underneath, to match the style of the rest of the art.
+    compositing::draw(
+        &mut body,
+        &RgbaImage::from_pixel(torso_width + 6, torso_height + 6, Rgba([0, 0, 0, 255])),
+        &Placement::at(torso_x - 3.0, torso_y - 3.0).mask(Mask::RoundedRect(43.0)),
+    );
+    compositing::draw(
+        &mut body,
+        &RgbaImage::from_pixel(torso_width, torso_height, FLIPPER_SHIRT),
+        &Placement::at(torso_x, torso_y).mask(Mask::RoundedRect(40.0)),
+    );
+    for (shoulder, hand) in FLIPPER_ARMS {
+        compositing::draw_line(&mut body, shoulder, hand, 12.0, Rgba([0, 0, 0, 255]));
+        compositing::draw_line(&mut body, shoulder, hand, 9.0, FLIPPER_SKIN);
+    }
+
+    let background = if to == JouchOrientation::Orbit {
+        starfield(FLIP_SCENE_SIZE.0, FLIP_SCENE_SIZE.1)
+    } else {
+        RgbaImage::from_pixel(FLIP_SCENE_SIZE.0, FLIP_SCENE_SIZE.1, Rgba([0, 0, 0, 255]))
+    };
+
+    vec![
+        Layer {
+            image: Animation::still(DynamicImage::ImageRgba8(background)),
+            placement: Placement::at(0.0, 0.0),
+        },
+        Layer {
+            image: Animation { frames },
+            placement: Placement::at(0.0, 0.0),
+        },
+        Layer {
+            image: Animation::still(DynamicImage::ImageRgba8(body)),
+            placement: Placement::at(0.0, 0.0),
+        },
+        Layer {
+            image: flipper,
+            placement: Placement::at(FLIPPER_POSITION.0 as f32, FLIPPER_POSITION.1 as f32)
+                .mask(Mask::Circle)
+                .shadow(Shadow::default()),
+        },
+    ]
+}
+
+// Someone sitting on The Jouch, with their avatar already sized for their seat.
+pub struct Seat {
+    pub face: Animation,
+    // whether it's their birthday
+    pub hat: bool,
+    // id of the accessory they're wearing, if any
+    pub accessory: Option<String>,
+}
+
+// The layers for a sit image: each panel's base image side by side, with everyone in their seat (in order)
+// wearing their hats & accessories. Also gives the size of the whole image.
+pub fn sit_layers(
+    assets: &JouchAssets,
+    panels: Vec<(&Scene, Animation)>,
+    seats: Vec<Seat>,
+) -> anyhow::Result<(Vec<Layer>, u32, u32)> {
+    // Lay the panels out left to right, keeping track of where each seat ended up.
+    let mut layers = Vec::new();
+    let mut seat_positions = Vec::new();
+    let mut top_layers = Vec::new();
+    let (mut width, mut height) = (0, 0);
+    for (scene, panel) in panels {
+        seat_positions.extend(
+            scene
+                .seats
+                .iter()
+                .map(|(x, y)| ((x + width) as i32, *y as i32, scene)),
+        );
+        height = height.max(panel.height());
+        let panel_width = panel.width();
+        layers.push(Layer {
+            image: panel,
+            placement: Placement::at(width as f32, 0.0),
+        });
+        for overlay in &scene.overlays {
+            let layer = Layer {
+                image: assets.get(&overlay.image)?.clone(),
+                placement: Placement::at(
+                    (overlay.position.0 + width as i32) as f32,
+                    overlay.position.1 as f32,
+                ),
+            };
+            if overlay.above_avatars {
+                top_layers.push(layer);
+            } else {
+                layers.push(layer);
+            }
+        }
+        width += panel_width;
+    }
+
+    for (
+        Seat {
+            face,
+            hat,
+            accessory,
+        },
+        (x, y, scene),
+    ) in seats.into_iter().zip(seat_positions)
+    {
+        layers.push(Layer {
+            image: face,
+            placement: Placement::at(x as f32, y as f32).mask(Mask::Circle),
+        });
+
+        let birthday_hat = scene.hat.as_ref().filter(|_| hat);
+
+        // accessories removed from the catalog are just left off.
+        let accessory = accessory
+            .as_deref()
+            .and_then(|id| assets.accessories.get(id))
+            // birthday hats win out over anything else on the head
+            .filter(|accessory| birthday_hat.is_none() || accessory.slot != Slot::Head);
+        if let Some(accessory) = accessory {
+            let scale = scene.avatar_size as f32 / REFERENCE_AVATAR_SIZE as f32;
+            layers.push(Layer {
+                image: assets.get(&accessory.image)?.clone(),
+                placement: Placement::at(
+                    x as f32 + accessory.anchor.0 as f32 * scale,
+                    y as f32 + accessory.anchor.1 as f32 * scale,
+                )
+                .scale(scale),
+            });
+        }
+
+        if let Some(scene_hat) = birthday_hat {
+            let scale = scene.avatar_size as f32
+                / scene_hat.avatar_size.unwrap_or(scene.avatar_size) as f32;
+            layers.push(Layer {
+                image: assets.get(&scene_hat.image)?.clone(),
+                placement: Placement::at(
+                    x as f32 + scene_hat.anchor.0 as f32 * scale,
+                    y as f32 + scene_hat.anchor.1 as f32 * scale,
+                )
+                .scale(scale),
+            });
+        }
+    }
+    layers.extend(top_layers);
+
+    Ok((layers, width, height))
+}
+
+// One row of the rankings card; rank is shared by anyone tied.
+pub struct CardEntry {
+    pub face: Animation,
+    pub rank: i64,
+    pub count: i32,
+}
+
+fn medal(rank: i64) -> RgbaImage {
+    let (fill, rim) = MEDAL_COLORS[(rank.max(1) as usize - 1).min(MEDAL_COLORS.len() - 1)];
+    let size = CARD_MEDAL_SIZE;
+    let mut medal = RgbaImage::new(size, size);
+    compositing::draw(
+        &mut medal,
+        &RgbaImage::from_pixel(size, size, rim),
+        &Placement::at(0.0, 0.0).mask(Mask::Circle),
+    );
+    compositing::draw(
+        &mut medal,
+        &RgbaImage::from_pixel(size - 12, size - 12, fill),
+        &Placement::at(6.0, 6.0).mask(Mask::Circle),
+    );
+
+    let digits = rank.to_string();
+    // keep long ranks inside the medal
+    let height = (size as f32 * 0.45).min(size as f32 * 0.6 / (digits.len() as f32 * 0.75));
+    compositing::draw_digits(
+        &mut medal,
+        &digits,
+        (
+            (size as f32 - compositing::digits_width(&digits, height)) / 2.0,
+            (size as f32 - height) / 2.0,
+        ),
+        height,
+        height / 12.0,
+        rim,
+    );
+    medal
+}
+
+// The layers for the /rankings card: The Jouch in its current orientation as a header,
+// then each entry's medal, avatar & count, with a little couch showing what was counted
+// (upside down for flips). Also gives the size of the whole image.
+pub fn card_layers(
+    couch: &RgbaImage,
+    orientation: JouchOrientation,
+    entries: Vec<CardEntry>,
+    flips: bool,
+) -> (Vec<Layer>, u32, u32) {
+    let width = CARD_WIDTH;
+    let height = CARD_HEADER_HEIGHT + CARD_ROW_HEIGHT * entries.len().max(1) as u32;
+
+    let mut background = RgbaImage::from_pixel(width, height, CARD_BACKGROUND);
+    let header = if orientation == JouchOrientation::Orbit {
+        starfield(width, CARD_HEADER_HEIGHT)
+    } else {
+        RgbaImage::from_pixel(width, CARD_HEADER_HEIGHT, CARD_HEADER)
+    };
+    compositing::draw(&mut background, &header, &Placement::at(0.0, 0.0));
+
+    // The Jouch, as big as fits in the header.
+    let jouch = orientation
+        .transform(DynamicImage::ImageRgba8(couch.clone()))
+        .into_rgba8();
+    let scale = ((CARD_HEADER_HEIGHT as f32 - CARD_PADDING) / jouch.height() as f32)
+        .min((width as f32 - CARD_PADDING * 2.0) / jouch.width() as f32);
+    compositing::draw(
+        &mut background,
+        &jouch,
+        &Placement::at(
+            (width as f32 - jouch.width() as f32 * scale) / 2.0,
+            (CARD_HEADER_HEIGHT as f32 - jouch.height() as f32 * scale) / 2.0,
+        )
+        .scale(scale),
+    );
+
+    let icon = if flips {
+        JouchOrientation::UpsideDown
+            .transform(DynamicImage::ImageRgba8(couch.clone()))
+            .into_rgba8()
+    } else {
+        couch.clone()
+    };
+    let icon_scale = CARD_ICON_HEIGHT / icon.height() as f32;
+
+    let mut layers = Vec::new();
+    for (i, entry) in entries.into_iter().enumerate() {
+        let top = (CARD_HEADER_HEIGHT + CARD_ROW_HEIGHT * i as u32) as f32;
+        let center = top + CARD_ROW_HEIGHT as f32 / 2.0;
+
+        if i % 2 == 1 {
+            compositing::draw(
+                &mut background,
+                &RgbaImage::from_pixel(width, CARD_ROW_HEIGHT, CARD_STRIPE),
+                &Placement::at(0.0, top),
+            );
+        }
+
+        compositing::draw(
+            &mut background,
+            &medal(entry.rank),
+            &Placement::at(CARD_PADDING, center - CARD_MEDAL_SIZE as f32 / 2.0)
+                .shadow(Shadow::default()),
+        );
+
+        let avatar_x = CARD_PADDING * 2.0 + CARD_MEDAL_SIZE as f32;
+        layers.push(Layer {
+            image: entry.face,
+            placement: Placement::at(avatar_x, center - CARD_AVATAR_SIZE as f32 / 2.0)
+                .mask(Mask::Circle)
+                .shadow(Shadow::default()),
+        });
+
+        let digits = entry.count.to_string();
+        let digits_x = avatar_x + CARD_AVATAR_SIZE as f32 + CARD_PADDING;
+        compositing::draw_digits(
+            &mut background,
+            &digits,
+            (digits_x, center - CARD_DIGIT_HEIGHT / 2.0),
+            CARD_DIGIT_HEIGHT,
+            CARD_DIGIT_HEIGHT / 14.0,
+            CARD_TEXT,
+        );
+
+        compositing::draw(
+            &mut background,
+            &icon,
+            &Placement::at(
+                width as f32 - CARD_PADDING - icon.width() as f32 * icon_scale,
+                center - CARD_ICON_HEIGHT / 2.0,
+            )
+            .scale(icon_scale),
+        );
+    }
+
+    layers.insert(
+        0,
+        Layer {
+            image: Animation::still(DynamicImage::ImageRgba8(background)),
+            placement: Placement::at(0.0, 0.0),
+        },
+    );
+
+    (layers, width, height)
+}
@@ -0,0 +1,231 @@
+// Scenes The Jouch can be drawn in, described by a manifest in the assets directory
+// so new poses & seasonal variants can be added without touching any code.
+use std::collections::HashSet;
+use std::path::Path;
+
+use anyhow::bail;
+use chrono::{Datelike, NaiveDate};
+use rand::{seq::IndexedRandom, Rng};
+use serde::Deserialize;
+
+pub const MANIFEST_FILE: &str = "scenes.ron";
+
+#[derive(Debug, Deserialize)]
+pub struct SceneManifest {
+    // the empty couch drawn by /flip & /rectify
+    pub empty_couch: String,
+    pub scenes: Vec<Scene>,
+}
+
+// A base image along with where everyone sits on it.
+#[derive(Debug, Deserialize)]
+pub struct Scene {
+    // used to tell scenes apart when caching, so must be unique
+    pub name: String,
+    pub base_image: String,
+    // top left corner of each seated avatar
+    pub seats: Vec<(u32, u32)>,
+    #[serde(default = "default_avatar_size")]
+    pub avatar_size: u32,
+    // what birthday users get to wear, if anything
+    #[serde(default)]
+    pub hat: Option<Hat>,
+    #[serde(default)]
+    pub overlays: Vec<Overlay>,
+    // when the scene is in season; scenes without one are used all year, whenever nothing seasonal is.
+    #[serde(default)]
+    pub active: Option<DateRange>,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct Hat {
+    pub image: String,
+    // where the top left corner of the hat goes, relative to the top left corner of the avatar
+    pub anchor: (i32, i32),
+    // the avatar size the hat was drawn for, if not the scene's own; it gets scaled to fit the scene's avatars.
+    #[serde(default)]
+    pub avatar_size: Option<u32>,
+}
+
+// Extra image drawn on top of the base image, e.g. decorations for a seasonal scene.
+#[derive(Debug, Deserialize)]
+pub struct Overlay {
+    pub image: String,
+    #[serde(default)]
+    pub position: (i32, i32),
+    // whether it goes over the avatars (& hats), or just over the base image.
+    #[serde(default)]
+    pub above_avatars: bool,
+}
+
+// Inclusive range of (month, day) pairs, which wraps around the end of the year if `to` comes before `from`.
+#[derive(Debug, Deserialize, Clone, Copy)]
+pub struct DateRange {
+    pub from: (u32, u32),
+    pub to: (u32, u32),
+}
+
+fn default_avatar_size() -> u32 {
+    128
+}
+
+impl DateRange {
+    pub fn contains(&self, date: NaiveDate) -> bool {
+        let day = (date.month(), date.day());
+        if self.from <= self.to {
+            self.from <= day && day <= self.to
+        } else {
+            day >= self.from || day <= self.to
+        }
+    }
+
+    fn is_valid(&self) -> bool {
+        // 2000 is a leap year, so Feb 29th is allowed.
+        [self.from, self.to]
+            .iter()
+            .all(|(month, day)| NaiveDate::from_ymd_opt(2000, *month, *day).is_some())
+    }
+}
+
+impl Scene {
+    fn is_seasonal(&self) -> bool {
+        self.active.is_some()
+    }
+
+    fn is_active(&self, date: NaiveDate) -> bool {
+        self.active.is_none_or(|range| range.contains(date))
+    }
+}
+
+impl SceneManifest {
+    pub fn load(dir: &Path) -> anyhow::Result<Self> {
+        let manifest: Self = ron::de::from_bytes(&std::fs::read(dir.join(MANIFEST_FILE))?)?;
+        manifest.validate()?;
+        Ok(manifest)
+    }
+
+    fn validate(&self) -> anyhow::Result<()> {
+        let mut names = HashSet::new();
+        for scene in &self.scenes {
+            if !names.insert(&scene.name) {
+                bail!("Scene name {} is used more than once", scene.name);
+            }
+            if scene.seats.is_empty() {
+                bail!("Scene {} has no seats", scene.name);
+            }
+            if scene.avatar_size == 0 {
+                bail!("Scene {} has an avatar size of 0", scene.name);
+            }
+            if scene.hat.as_ref().and_then(|hat| hat.avatar_size) == Some(0) {
+                bail!("Scene {} has a hat with an avatar size of 0", scene.name);
+            }
+            if scene.active.is_some_and(|range| !range.is_valid()) {
+                bail!("Scene {} has an invalid date range", scene.name);
+            }
+        }
+
+        // anything else could leave some number of people with nowhere to sit.
+        if !self
+            .scenes
+            .iter()
+            .any(|scene| !scene.is_seasonal() && scene.seats.len() == 1)
+        {
+            bail!("There must be a single seat scene that isn't seasonal");
+        }
+
+        Ok(())
+    }
+
+    // Every image the manifest refers to.
+    pub fn images(&self) -> impl Iterator<Item = &str> {
+        std::iter::once(self.empty_couch.as_str()).chain(self.scenes.iter().flat_map(|scene| {
+            std::iter::once(scene.base_image.as_str())
+                .chain(scene.hat.iter().map(|hat| hat.image.as_str()))
+                .chain(scene.overlays.iter().map(|overlay| overlay.image.as_str()))
+        }))
+    }
+
+    // Birthday hat for scenes that don't come from the manifest, i.e. guild skins; taken from the single seat scene.
+    pub fn default_hat(&self) -> Option<Hat> {
+        self.scenes
+            .iter()
+            .find(|scene| !scene.is_seasonal() && scene.seats.len() == 1)
+            .and_then(|scene| {
+                scene.hat.clone().map(|hat| Hat {
+                    avatar_size: hat.avatar_size.or(Some(scene.avatar_size)),
+                    ..hat
+                })
+            })
+    }
+
+    // Pick the scenes needed to seat everyone, which get put side by side when there's more than one.
+    // Fills the biggest scenes first, so e.g. 3 people end up as a pair and a single.
+    // A guild's own skin is used over anything in season, which is used over the all year scenes;
+    // each is only used for numbers of people the ones before it can't fit. A skin can also seat
+    // fewer people than it has seats, leaving the rest empty, as long as it's the last panel.
+    pub fn panels<'a>(
+        &'a self,
+        count: usize,
+        date: NaiveDate,
+        skin: Option<&'a Scene>,
+        rng: &mut impl Rng,
+    ) -> Vec<&'a Scene> {
+        let seasonal: Vec<&Scene> = self
+            .scenes
+            .iter()
+            .filter(|scene| scene.is_seasonal() && scene.is_active(date))
+            .collect();
+        let all_year: Vec<&Scene> = self
+            .scenes
+            .iter()
+            .filter(|scene| !scene.is_seasonal())
+            .collect();
+        let pools = [skin.into_iter().collect(), seasonal, all_year];
+
+        let mut panels = Vec::new();
+        let mut remaining = count.max(1);
+
+        while remaining > 0 {
+            if let Some(skin) = skin.filter(|skin| skin.seats.len() >= remaining) {
+                panels.push(skin);
+                break;
+            }
+
+            let fits = |scenes: &[&'_ Scene]| -> Option<usize> {
+                scenes
+                    .iter()
+                    .map(|scene| scene.seats.len())
+                    .filter(|seats| *seats <= remaining)
+                    .max()
+            };
+            let Some((pool, seats)) = pools
+                .iter()
+                .find_map(|pool| fits(pool).map(|seats| (pool, seats)))
+            else {
+                // can't happen once validated, since there's always an all year single seat scene.
+                break;
+            };
+
+            // choose between any scenes with the same number of seats, for some variety.
+            let options: Vec<&Scene> = pool
+                .iter()
+                .copied()
+                .filter(|scene| scene.seats.len() == seats)
+                .collect();
+            if let Some(scene) = options.choose(rng) {
+                panels.push(*scene);
+            }
+            remaining -= seats;
+        }
+
+        panels
+    }
+}
+
+// Size of the avatar for each seat across all the panels, in order.
+pub fn avatar_sizes(panels: &[&Scene]) -> Vec<u32> {
+    panels
+        .iter()
+        .flat_map(|scene| std::iter::repeat_n(scene.avatar_size, scene.seats.len()))
+        .collect()
+}
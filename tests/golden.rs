@@ -0,0 +1,85 @@
+// Renders fixed sits & compares them to reference images in tests/golden, so changes to drawing don't go unnoticed.
+// Run with UPDATE_GOLDEN=1 to write new references after changing how things are meant to look.
+use std::path::{Path, PathBuf};
+
+use image::{DynamicImage, Rgba, RgbaImage};
+use the_jouch::render::{render_scene, sit_layers, Animation, JouchAssets, JouchOrientation, Seat};
+use the_jouch::scenes::avatar_sizes;
+
+// A made up avatar, so the test doesn't depend on anyone's real one.
+fn avatar(color: [u8; 3], size: u32) -> Animation {
+    let image = RgbaImage::from_fn(size, size, |x, y| {
+        let shade = ((x + y) * 255 / (size * 2)) as u8;
+        Rgba([
+            color[0].saturating_sub(shade),
+            color[1].saturating_sub(shade),
+            color[2].saturating_sub(shade),
+            255,
+        ])
+    });
+    Animation::still(DynamicImage::ImageRgba8(image))
+}
+
+fn check_golden(
+    name: &str,
+    scene: &str,
+    seats: Vec<(bool, Option<&str>)>,
+    orientation: JouchOrientation,
+) {
+    let assets = JouchAssets::load(Path::new("assets")).unwrap();
+    let scene = assets
+        .scenes
+        .scenes
+        .iter()
+        .find(|candidate| candidate.name == scene)
+        .unwrap();
+    let panels = vec![scene];
+
+    let colors = [[230, 90, 80], [80, 150, 230], [120, 210, 100]];
+    let seats = seats
+        .into_iter()
+        .zip(avatar_sizes(&panels))
+        .zip(colors.iter())
+        .map(|(((hat, accessory), size), color)| Seat {
+            face: avatar(*color, size),
+            hat,
+            accessory: accessory.map(str::to_owned),
+        })
+        .collect();
+    let panel_images = panels
+        .iter()
+        .map(|scene| (*scene, assets.get(&scene.base_image).unwrap().clone()))
+        .collect();
+
+    let (layers, width, height) = sit_layers(&assets, panel_images, seats).unwrap();
+    let (image_bytes, _) = render_scene(&layers, width, height, orientation).unwrap();
+    let actual = image::load_from_memory(&image_bytes).unwrap().into_rgba8();
+
+    let reference_path = PathBuf::from("tests/golden").join(format!("{name}.png"));
+    if std::env::var_os("UPDATE_GOLDEN").is_some() {
+        actual.save(&reference_path).unwrap();
+        return;
+    }
+    let reference = image::open(&reference_path)
+        .unwrap_or_else(|err| panic!("Unable to load {:?}: {}", reference_path, err))
+        .into_rgba8();
+
+    if actual != reference {
+        let actual_path = std::env::temp_dir().join(format!("{name}-actual.png"));
+        actual.save(&actual_path).unwrap();
+        panic!(
+            "{name} doesn't match {:?}; what got drawn is in {:?}",
+            reference_path, actual_path
+        );
+    }
+}
+
+#[test]
+fn pair_upside_down() {
+    check_golden(
+        "pair-upside-down",
+        "pair",
+        vec![(true, None), (false, Some("top-hat"))],
+        JouchOrientation::UpsideDown,
+    );
+}